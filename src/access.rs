@@ -0,0 +1,241 @@
+use crate::block::{At, AtBlock};
+use crate::client::{find_event, MubertClient};
+use crate::dispatch::TxOutcome;
+use crate::error::Error;
+use crate::ip_onchain_runtime::ip_onchain::events::{
+    AuthoritiesAccessAdded, AuthoritiesAccessChanged,
+};
+use crate::ip_onchain_runtime::runtime_types::pallet_ip_onchain::types::{
+    AuthorityAccessSetting, BitFlags,
+};
+use crate::{has_flag, ip_onchain_runtime, Bitmask};
+use serde::Serialize;
+use subxt::utils::AccountId32;
+
+const ACCESS_SETTINGS: [AuthorityAccessSetting; 6] = [
+    AuthorityAccessSetting::EditAccess,
+    AuthorityAccessSetting::CreateEntity,
+    AuthorityAccessSetting::EditEntity,
+    AuthorityAccessSetting::EditAuthority,
+    AuthorityAccessSetting::CreateAuthorityCollection,
+    AuthorityAccessSetting::WrapEntity,
+];
+
+#[derive(clap::Args, Clone, Default)]
+pub struct AccessSettings {
+    #[arg(long, help = "may grant and edit access of other accounts")]
+    pub edit_access: bool,
+    #[arg(long)]
+    pub create_entity: bool,
+    #[arg(long)]
+    pub edit_entity: bool,
+    #[arg(long)]
+    pub edit_authority: bool,
+    #[arg(long)]
+    pub create_authority_collection: bool,
+    #[arg(long)]
+    pub wrap_entity: bool,
+}
+
+impl Bitmask for AccessSettings {
+    fn bitmask(&self) -> u64 {
+        [
+            (self.edit_access, AuthorityAccessSetting::EditAccess),
+            (self.create_entity, AuthorityAccessSetting::CreateEntity),
+            (self.edit_entity, AuthorityAccessSetting::EditEntity),
+            (self.edit_authority, AuthorityAccessSetting::EditAuthority),
+            (
+                self.create_authority_collection,
+                AuthorityAccessSetting::CreateAuthorityCollection,
+            ),
+            (self.wrap_entity, AuthorityAccessSetting::WrapEntity),
+        ]
+        .iter()
+        .filter(|(enabled, _)| *enabled)
+        .fold(0, |result, (_, setting)| result | setting.bitmask())
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct AccountAccess {
+    pub authority_id: u32,
+    pub account_id: AccountId32,
+    /// Names of the granted `AuthorityAccessSetting`s.
+    pub access: Vec<String>,
+}
+
+impl AccountAccess {
+    fn new(authority_id: u32, account_id: AccountId32, access: u64) -> Self {
+        AccountAccess {
+            authority_id,
+            account_id,
+            access: access_names(access),
+        }
+    }
+}
+
+fn access_names(access: u64) -> Vec<String> {
+    ACCESS_SETTINGS
+        .iter()
+        .filter(|setting| has_flag(access, (*setting).clone()))
+        .map(|setting| format!("{setting:?}"))
+        .collect()
+}
+
+impl MubertClient {
+    pub async fn create_account_access(
+        &self,
+        authority_id: u32,
+        account_id: &AccountId32,
+        access: &AccessSettings,
+    ) -> Result<TxOutcome<AuthoritiesAccessAdded>, Error> {
+        let call = ip_onchain_runtime::tx().ip_onchain().create_account_access(
+            authority_id,
+            account_id.clone(),
+            BitFlags(access.bitmask(), Default::default()),
+        );
+        self.submit(&call)
+            .await?
+            .and_then(|events| find_event(&events))
+    }
+
+    pub async fn edit_account_access(
+        &self,
+        authority_id: u32,
+        account_id: &AccountId32,
+        access: &AccessSettings,
+    ) -> Result<TxOutcome<AuthoritiesAccessChanged>, Error> {
+        let call = ip_onchain_runtime::tx().ip_onchain().edit_account_access(
+            authority_id,
+            account_id.clone(),
+            BitFlags(access.bitmask(), Default::default()),
+        );
+        self.submit(&call)
+            .await?
+            .and_then(|events| find_event(&events))
+    }
+
+    pub async fn get_account_access(
+        &self,
+        authority_id: u32,
+        account_id: &AccountId32,
+        at: &Option<At>,
+    ) -> Result<AtBlock<AccountAccess>, Error> {
+        let block = self.block(at).await?;
+        let query = ip_onchain_runtime::storage()
+            .ip_onchain()
+            .authorities_access(authority_id, account_id);
+
+        let access = block
+            .storage()
+            .fetch(&query)
+            .await?
+            .ok_or_else(|| Error::NotFound("account access not found".into()))?;
+        Ok(AtBlock::new(
+            &block,
+            AccountAccess::new(authority_id, account_id.clone(), access.0),
+        ))
+    }
+}
+
+pub async fn create_account_access(
+    client: &MubertClient,
+    authority_id: u32,
+    account_id: &AccountId32,
+    access: &AccessSettings,
+) -> Result<(), Error> {
+    println!("Submitting transaction...");
+    let Some(event) = client
+        .create_account_access(authority_id, account_id, access)
+        .await?
+        .print_unsent()?
+    else {
+        return Ok(());
+    };
+    println!("Account access added successful: {:?}", event);
+    Ok(())
+}
+
+pub async fn edit_account_access(
+    client: &MubertClient,
+    authority_id: u32,
+    account_id: &AccountId32,
+    access: &AccessSettings,
+) -> Result<(), Error> {
+    println!("Submitting transaction...");
+    let Some(event) = client
+        .edit_account_access(authority_id, account_id, access)
+        .await?
+        .print_unsent()?
+    else {
+        return Ok(());
+    };
+    println!("Account access changed successful: {:?}", event);
+    Ok(())
+}
+
+pub async fn get_account_access(
+    client: &MubertClient,
+    authority_id: u32,
+    account_id: &AccountId32,
+    at: &Option<At>,
+) -> Result<(), Error> {
+    let access = client
+        .get_account_access(authority_id, account_id, at)
+        .await?;
+    println!("{}", serde_json::to_string(&access)?);
+    Ok(())
+}
+
+pub async fn list_account_access(
+    client: &MubertClient,
+    authority_id: u32,
+    at: &Option<At>,
+) -> Result<(), Error> {
+    let block = client.block(at).await?;
+
+    let query = ip_onchain_runtime::storage()
+        .ip_onchain()
+        .authorities_access_iter1(authority_id);
+
+    let mut results = block.storage().iter(query).await?;
+
+    while let Some(kv) = results.next().await {
+        let kv = kv?;
+
+        // the account id is the last key, Blake2_128Concat keeps it raw at the end
+        let account_id: [u8; 32] = kv
+            .key_bytes
+            .get(kv.key_bytes.len().saturating_sub(32)..)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| Error::Decode("bad authorities access storage key".into()))?;
+
+        let data = serde_json::to_string(&AtBlock::new(
+            &block,
+            AccountAccess::new(authority_id, AccountId32(account_id), kv.value.0),
+        ))?;
+
+        println!("{data}");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_access_settings_bitmask() {
+        let access = AccessSettings {
+            create_entity: true,
+            wrap_entity: true,
+            ..Default::default()
+        };
+        assert_eq!(access.bitmask(), 0x00000022);
+        assert_eq!(
+            access_names(access.bitmask()),
+            ["CreateEntity", "WrapEntity"]
+        );
+    }
+}
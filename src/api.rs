@@ -1,8 +1,17 @@
+use crate::error::Error;
+use crate::poll::{poll_until, Poll, PollOptions, Waiting};
+use crate::upload::UploadOptions;
 use serde::{Deserialize, Serialize};
-use std::error::Error;
-use std::fs;
-use tokio_retry::strategy::FixedInterval;
-use tokio_retry::Retry;
+use std::path::Path;
+use std::time::Duration;
+
+/// Error status of the fingerprint api with the answer body, kept for the caller to inspect.
+pub(crate) async fn status_error(response: reqwest::Response) -> Error {
+    Error::Api {
+        status: response.status().as_u16(),
+        body: response.text().await.unwrap_or_default(),
+    }
+}
 
 #[derive(Deserialize, Debug)]
 pub struct StatusResponse {
@@ -11,84 +20,15 @@ pub struct StatusResponse {
     pub url: String,
 }
 
-pub async fn check_fingerprint_status(
-    task_id: &String,
-    auth_token: &String,
-) -> Result<StatusResponse, Box<dyn Error>> {
-    let client = reqwest::Client::new();
-    let url = format!("https://fingerprint.mubert.xyz/v1/fingerprint/status?task_id={task_id}");
-
-    let response = client
-        .get(&url)
-        .header(
-            reqwest::header::AUTHORIZATION,
-            format!("Bearer {auth_token}"),
-        )
-        .send()
-        .await?;
-
-    if !response.status().is_success() {
-        return Err(format!("Request failed with status: {}", response.status()).into());
-    }
-
-    Ok(response.json().await?)
-}
-
-pub async fn wait_for_fingerprint_url(
-    task_id: &String,
-    timeout_secs: u64,
-    auth_token: &String,
-) -> Result<String, Box<dyn Error>> {
-    Retry::spawn(FixedInterval::from_millis(timeout_secs * 1000), || async {
-        match check_fingerprint_status(task_id, auth_token).await {
-            Ok(result) => {
-                println!("{result:?}");
-                if result.status == "done" {
-                    Ok(result.url)
-                } else {
-                    Err("fingerprint not ready".into())
-                }
-            }
-            Err(e) => {
-                println!("{e:?}");
-                Err(e)
-            }
-        }
-    })
-    .await
-}
-
 #[derive(Deserialize, Debug)]
 pub struct JobResponse {
     pub id: String,
+    /// The job of the same content from the job cache, nothing was uploaded.
+    #[serde(default)]
+    pub reused: bool,
 }
 
-pub async fn upload_audio(
-    file_path: &std::path::PathBuf,
-    auth_token: &String,
-) -> Result<JobResponse, Box<dyn Error>> {
-    let buffer = fs::read(file_path)?;
-
-    let client = reqwest::Client::new();
-    let response = client
-        .put("https://fingerprint.mubert.xyz/v1/fingerprint/create")
-        .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
-        .header(
-            reqwest::header::AUTHORIZATION,
-            format!("Bearer {auth_token}"),
-        )
-        .body(buffer)
-        .send()
-        .await?;
-
-    if !response.status().is_success() {
-        return Err(format!("Request failed with status: {}", response.status()).into());
-    }
-
-    Ok(response.json().await?)
-}
-
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub struct MetadataRequest {
     pub title: String,
     pub bpm: u32,
@@ -103,29 +43,126 @@ pub struct MetadataResponse {
     pub url: String,
 }
 
-pub async fn create_metadata(
-    payload: MetadataRequest,
-    auth_token: &String,
-) -> Result<MetadataResponse, Box<dyn Error>> {
-    let client = reqwest::Client::new();
-    let response = client
-        .post("https://fingerprint.mubert.xyz/v1/metadata/create")
-        .header(
-            reqwest::header::AUTHORIZATION,
-            format!("Bearer {auth_token}"),
-        )
-        .json(&payload)
-        .send()
-        .await?;
-
-    if !response.status().is_success() {
-        return Err(format!(
-            "Request failed with status: {} {}",
-            response.status(),
-            response.text().await.unwrap()
-        )
-        .into());
+/// Client of the off chain fingerprint and metadata api, it needs no node.
+pub struct FingerprintApi {
+    http: reqwest::Client,
+    api_url: String,
+    auth_token: Option<String>,
+    request_timeout: Duration,
+    poll: PollOptions,
+    upload: UploadOptions,
+}
+
+impl FingerprintApi {
+    /// `api_url` is the base url, like `https://fingerprint.mubert.xyz`.
+    pub fn new(
+        api_url: &str,
+        auth_token: Option<String>,
+        request_timeout: Duration,
+    ) -> Result<Self, Error> {
+        let http = reqwest::Client::builder()
+            .timeout(request_timeout)
+            .build()?;
+        Ok(FingerprintApi {
+            http,
+            api_url: api_url.trim_end_matches('/').to_string(),
+            auth_token,
+            request_timeout,
+            poll: PollOptions::every(Duration::from_secs(10)),
+            upload: UploadOptions::default(),
+        })
+    }
+
+    /// Polling of the fingerprint status, from every 10s by default.
+    pub fn with_poll_options(mut self, poll: PollOptions) -> Self {
+        self.poll = poll;
+        self
+    }
+
+    pub fn with_upload_options(mut self, upload: UploadOptions) -> Self {
+        self.upload = upload;
+        self
+    }
+
+    pub fn api_url(&self) -> &str {
+        &self.api_url
     }
 
-    Ok(response.json().await?)
+    pub(crate) fn http(&self) -> &reqwest::Client {
+        &self.http
+    }
+
+    pub(crate) fn request_timeout(&self) -> Duration {
+        self.request_timeout
+    }
+
+    pub(crate) fn upload_options(&self) -> &UploadOptions {
+        &self.upload
+    }
+
+    pub(crate) fn endpoint(&self, path: &str) -> String {
+        format!("{}{path}", self.api_url)
+    }
+
+    pub(crate) fn auth_token(&self) -> Result<&str, Error> {
+        self.auth_token
+            .as_deref()
+            .ok_or_else(|| Error::Config("no fingerprint api auth token given".into()))
+    }
+
+    pub async fn check_fingerprint_status(&self, task_id: &str) -> Result<StatusResponse, Error> {
+        let response = self
+            .http
+            .get(self.endpoint("/v1/fingerprint/status"))
+            .query(&[("task_id", task_id)])
+            .bearer_auth(self.auth_token()?)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(status_error(response).await);
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Polls the fingerprint job until it is done and returns the fingerprint url,
+    /// a `failed` job is `Error::JobFailed`.
+    pub async fn wait_for_fingerprint_url(&self, task_id: &str) -> Result<String, Error> {
+        let waiting = Waiting::FingerprintJob(task_id.to_string());
+        poll_until(&self.poll, waiting.clone(), || async {
+            let result = self.check_fingerprint_status(task_id).await?;
+            match result.status.as_str() {
+                "done" => Ok(Poll::Ready(result.url)),
+                "failed" => Err(Error::JobFailed(waiting.clone())),
+                _ => Ok(Poll::Pending(result.status)),
+            }
+        })
+        .await
+    }
+
+    /// Uploads the audio and waits for the fingerprint url.
+    pub async fn fingerprint(&self, file: &Path) -> Result<String, Error> {
+        let job = self.upload_audio(file).await?;
+        self.wait_for_fingerprint_url(&job.id).await
+    }
+
+    pub async fn create_metadata(
+        &self,
+        payload: &MetadataRequest,
+    ) -> Result<MetadataResponse, Error> {
+        let response = self
+            .http
+            .post(self.endpoint("/v1/metadata/create"))
+            .bearer_auth(self.auth_token()?)
+            .json(payload)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(status_error(response).await);
+        }
+
+        Ok(response.json().await?)
+    }
 }
@@ -0,0 +1,422 @@
+use crate::block::{At, AtBlock};
+use crate::client::{find_event, MubertClient};
+use crate::dispatch::TxOutcome;
+use crate::error::Error;
+use crate::ip_onchain_runtime;
+use crate::ip_onchain_runtime::arweave::events::TaskAdded;
+use crate::ip_onchain_runtime::arweave::storage::types::tasks::Tasks;
+use crate::ip_onchain_runtime::runtime_types::bounded_collections::bounded_vec::BoundedVec;
+use crate::ip_onchain_runtime::runtime_types::pallet_arweave::types::TaskState;
+use crate::list::Pagination;
+use crate::poll::{poll_until, Poll, Waiting};
+use serde::Serialize;
+use std::path::PathBuf;
+use subxt::utils::AccountId32;
+
+/// Url arweave serves the data of a tx at, followed by the tx hash.
+pub const ARWEAVE_URL: &str = "https://arweave.net/";
+
+/// Amount paid to the worker for a task when none is given.
+pub const DEFAULT_AMOUNT: u128 = 1_000_000;
+/// Tips of a task when none are given.
+pub const DEFAULT_TIPS: u128 = 1_000;
+
+/// Task of the `Tasks` storage with its data and tx hash as text.
+#[derive(Serialize, Debug)]
+pub struct ArweaveTask {
+    pub task_id: u64,
+    pub worker_address: AccountId32,
+    pub state: TaskState,
+    pub data: String,
+    pub tx_hash: Option<String>,
+    pub amount: u128,
+    pub tips: u128,
+    /// Data signed by the worker, hex, only read by `get_arweave_task`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signed_data: Option<String>,
+    /// Tx hash of the `TasksResults`, only read by `get_arweave_task`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result_tx_hash: Option<String>,
+}
+
+impl From<Tasks> for ArweaveTask {
+    fn from(task: Tasks) -> Self {
+        ArweaveTask {
+            task_id: task.task_id,
+            worker_address: task.worker_address,
+            state: task.state,
+            data: String::from_utf8_lossy(&task.data.0).to_string(),
+            tx_hash: task
+                .tx_hash
+                .map(|tx_hash| String::from_utf8_lossy(&tx_hash.0).to_string()),
+            amount: task.amount,
+            tips: task.tips,
+            signed_data: None,
+            result_tx_hash: None,
+        }
+    }
+}
+
+/// State change of a task, the state is `None` once the task is removed.
+#[derive(Debug)]
+pub struct TaskTransition {
+    pub task_id: u64,
+    pub before: TaskState,
+    pub after: Option<TaskState>,
+}
+
+impl std::fmt::Display for TaskTransition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.after {
+            Some(after) => write!(
+                f,
+                "arweave task {}: {:?} -> {:?}",
+                self.task_id, self.before, after
+            ),
+            None => write!(
+                f,
+                "arweave task {}: {:?} -> removed",
+                self.task_id, self.before
+            ),
+        }
+    }
+}
+
+impl MubertClient {
+    /// Gives the data to the arweave worker, returns the task id.
+    pub async fn create_arweave_task(
+        &self,
+        worker_address: &AccountId32,
+        data: Vec<u8>,
+        amount: u128,
+        tips: u128,
+    ) -> Result<TxOutcome<u64>, Error> {
+        let call = ip_onchain_runtime::tx().arweave().create_task(
+            worker_address.clone(),
+            BoundedVec(data),
+            amount,
+            tips,
+        );
+        self.submit(&call)
+            .await?
+            .and_then(|events| Ok(find_event::<TaskAdded>(&events)?.task_id))
+    }
+
+    pub async fn get_arweave_task(
+        &self,
+        task_id: u64,
+        at: &Option<At>,
+    ) -> Result<AtBlock<ArweaveTask>, Error> {
+        let block = self.block(at).await?;
+        let storage = ip_onchain_runtime::storage().arweave();
+
+        let task = block
+            .storage()
+            .fetch(&storage.tasks(task_id))
+            .await?
+            .ok_or_else(|| Error::NotFound(format!("arweave task {task_id} not found")))?;
+        let signed_data = block
+            .storage()
+            .fetch(&storage.tasks_signed_data(task_id))
+            .await?;
+        let result = block
+            .storage()
+            .fetch(&storage.tasks_results(task_id))
+            .await?;
+
+        let mut task = ArweaveTask::from(task);
+        task.signed_data = signed_data.map(|data| format!("0x{}", hex::encode(data.0)));
+        task.result_tx_hash =
+            result.map(|result| String::from_utf8_lossy(&result.tx_hash.0).to_string());
+        Ok(AtBlock::new(&block, task))
+    }
+
+    /// Tasks of one worker if given, in storage order or by id with `pagination.sorted`.
+    pub async fn list_arweave_tasks(
+        &self,
+        worker_address: Option<&AccountId32>,
+        state: Option<&TaskState>,
+        pagination: &Pagination,
+        at: &Option<At>,
+    ) -> Result<Vec<AtBlock<ArweaveTask>>, Error> {
+        let block = self.block(at).await?;
+        let query = ip_onchain_runtime::storage().arweave().tasks_iter();
+
+        let mut results = block.storage().iter(query).await?;
+
+        let mut tasks = Vec::new();
+        while let Some(kv) = results.next().await {
+            let task = kv?.value;
+            if worker_address.is_some_and(|worker| task.worker_address != *worker) {
+                continue;
+            }
+            if state.is_some_and(|state| task.state != *state) {
+                continue;
+            }
+            tasks.push(ArweaveTask::from(task));
+        }
+
+        if pagination.sorted {
+            tasks.sort_by_key(|task| task.task_id);
+        }
+        Ok(tasks
+            .into_iter()
+            .filter(|task| {
+                pagination
+                    .start_after
+                    .is_none_or(|start| task.task_id > start)
+            })
+            .take(pagination.limit.unwrap_or(usize::MAX))
+            .map(|task| AtBlock::new(&block, task))
+            .collect())
+    }
+
+    /// Registered workers which can take tasks.
+    pub async fn list_arweave_workers(&self, at: &Option<At>) -> Result<Vec<AccountId32>, Error> {
+        let block = self.block(at).await?;
+        let query = ip_onchain_runtime::storage().arweave().workers_iter();
+
+        let mut results = block.storage().iter(query).await?;
+
+        let mut workers = Vec::new();
+        while let Some(kv) = results.next().await {
+            workers.push(kv?.value.worker_address);
+        }
+        Ok(workers)
+    }
+
+    /// Current state of the task, `None` once removed.
+    async fn arweave_task_state(&self, task_id: u64) -> Result<Option<TaskState>, Error> {
+        let query = ip_onchain_runtime::storage().arweave().tasks(task_id);
+        let task = self
+            .api()
+            .storage()
+            .at_latest()
+            .await?
+            .fetch(&query)
+            .await?;
+        Ok(task.map(|task| task.state))
+    }
+
+    /// Submits a call changing the task, returns its state before and after.
+    async fn change_arweave_task<Call: subxt::tx::Payload>(
+        &self,
+        task_id: u64,
+        call: &Call,
+    ) -> Result<TxOutcome<TaskTransition>, Error> {
+        let before = self
+            .arweave_task_state(task_id)
+            .await?
+            .ok_or_else(|| Error::NotFound(format!("arweave task {task_id} not found")))?;
+        if let Err(outcome) = self.submit(call).await?.into_submitted() {
+            return Ok(outcome);
+        }
+        Ok(TxOutcome::Submitted(TaskTransition {
+            task_id,
+            before,
+            after: self.arweave_task_state(task_id).await?,
+        }))
+    }
+
+    pub async fn update_arweave_task(
+        &self,
+        task_id: u64,
+        state: TaskState,
+    ) -> Result<TxOutcome<TaskTransition>, Error> {
+        let call = ip_onchain_runtime::tx()
+            .arweave()
+            .update_task(task_id, state);
+        self.change_arweave_task(task_id, &call).await
+    }
+
+    /// Data signed by the worker for the arweave upload, with the arweave tx hash once known.
+    pub async fn sign_arweave_task(
+        &self,
+        task_id: u64,
+        signed_data: Vec<u8>,
+        tx_hash: Option<String>,
+    ) -> Result<TxOutcome<TaskTransition>, Error> {
+        let call = ip_onchain_runtime::tx().arweave().sign_task_data(
+            task_id,
+            BoundedVec(signed_data),
+            tx_hash.map(BoundedVec::from),
+        );
+        self.change_arweave_task(task_id, &call).await
+    }
+
+    /// Removes a task in the `Clear` state.
+    pub async fn clear_arweave_task(
+        &self,
+        task_id: u64,
+    ) -> Result<TxOutcome<TaskTransition>, Error> {
+        let call = ip_onchain_runtime::tx().arweave().clear_task(task_id);
+        self.change_arweave_task(task_id, &call).await
+    }
+
+    /// Polls the arweave task until the worker validated it and returns the arweave url.
+    pub async fn wait_for_arweave_url(&self, task_id: u64) -> Result<String, Error> {
+        let tasks_query = ip_onchain_runtime::storage().arweave().tasks(task_id);
+
+        poll_until(
+            self.arweave_poll(),
+            Waiting::ArweaveTask(task_id),
+            || async {
+                let tasks_details = self
+                    .api()
+                    .storage()
+                    .at_latest()
+                    .await?
+                    .fetch(&tasks_query)
+                    .await?
+                    .ok_or_else(|| Error::NotFound(format!("arweave task {task_id} not found")))?;
+
+                match (tasks_details.state, tasks_details.tx_hash) {
+                    (TaskState::Validate, Some(tx_hash)) => {
+                        let tx_hash = String::from_utf8(tx_hash.0)
+                            .map_err(|e| Error::Decode(format!("arweave tx hash: {e}")))?;
+                        Ok(Poll::Ready(format!("{ARWEAVE_URL}{tx_hash}")))
+                    }
+                    (state, _) => Ok(Poll::Pending(format!("{state:?}"))),
+                }
+            },
+        )
+        .await
+    }
+}
+
+fn read_payload(data: &Option<String>, data_file: &Option<PathBuf>) -> Result<Vec<u8>, Error> {
+    match (data, data_file) {
+        (Some(data), None) => Ok(data.clone().into_bytes()),
+        (None, Some(data_file)) => std::fs::read(data_file)
+            .map_err(|e| Error::Config(format!("read data_file {:?}: {e}", data_file))),
+        _ => Err(Error::Config("give --data or --data-file".into())),
+    }
+}
+
+pub async fn create_task(
+    client: &MubertClient,
+    worker_address: &AccountId32,
+    data: &Option<String>,
+    data_file: &Option<PathBuf>,
+    amount: u128,
+    tips: u128,
+) -> Result<(), Error> {
+    let payload = read_payload(data, data_file)?;
+
+    println!("Submitting transaction...");
+    let Some(task_id) = client
+        .create_arweave_task(worker_address, payload, amount, tips)
+        .await?
+        .print_unsent()?
+    else {
+        return Ok(());
+    };
+    println!("Arweave task added successful: {task_id}");
+    get_task(client, task_id, &None).await
+}
+
+pub async fn get_task(client: &MubertClient, task_id: u64, at: &Option<At>) -> Result<(), Error> {
+    let task = client.get_arweave_task(task_id, at).await?;
+    println!("{}", serde_json::to_string(&task)?);
+    Ok(())
+}
+
+pub async fn list_tasks(
+    client: &MubertClient,
+    worker_address: &Option<AccountId32>,
+    state: &Option<TaskState>,
+    pagination: &Pagination,
+    at: &Option<At>,
+) -> Result<(), Error> {
+    let tasks = client
+        .list_arweave_tasks(worker_address.as_ref(), state.as_ref(), pagination, at)
+        .await?;
+    for task in tasks {
+        println!("{}", serde_json::to_string(&task)?);
+    }
+    Ok(())
+}
+
+pub async fn list_workers(client: &MubertClient, at: &Option<At>) -> Result<(), Error> {
+    for worker in client.list_arweave_workers(at).await? {
+        println!("{worker}");
+    }
+    Ok(())
+}
+
+pub async fn update_task(
+    client: &MubertClient,
+    task_id: u64,
+    state: &TaskState,
+) -> Result<(), Error> {
+    println!("Submitting transaction...");
+    let Some(transition) = client
+        .update_arweave_task(task_id, state.clone())
+        .await?
+        .print_unsent()?
+    else {
+        return Ok(());
+    };
+    println!("{transition}");
+    Ok(())
+}
+
+pub async fn sign_task(
+    client: &MubertClient,
+    task_id: u64,
+    signed_data: &str,
+    tx_hash: &Option<String>,
+) -> Result<(), Error> {
+    let signed_data = hex::decode(signed_data.trim_start_matches("0x"))?;
+
+    println!("Submitting transaction...");
+    let Some(transition) = client
+        .sign_arweave_task(task_id, signed_data, tx_hash.clone())
+        .await?
+        .print_unsent()?
+    else {
+        return Ok(());
+    };
+    println!("{transition}");
+    Ok(())
+}
+
+pub async fn clear_task(client: &MubertClient, task_id: u64) -> Result<(), Error> {
+    println!("Submitting transaction...");
+    let Some(transition) = client.clear_arweave_task(task_id).await?.print_unsent()? else {
+        return Ok(());
+    };
+    println!("{transition}");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transition() {
+        let transition = TaskTransition {
+            task_id: 3,
+            before: TaskState::Upload,
+            after: Some(TaskState::Validate),
+        };
+        assert_eq!(transition.to_string(), "arweave task 3: Upload -> Validate");
+
+        let transition = TaskTransition {
+            task_id: 3,
+            before: TaskState::Clear,
+            after: None,
+        };
+        assert_eq!(transition.to_string(), "arweave task 3: Clear -> removed");
+    }
+
+    #[test]
+    fn test_read_payload() {
+        assert_eq!(
+            read_payload(&Some("{\"a\":1}".into()), &None).unwrap(),
+            b"{\"a\":1}".to_vec()
+        );
+        assert!(matches!(read_payload(&None, &None), Err(Error::Config(_))));
+    }
+}
@@ -0,0 +1,108 @@
+use crate::block::{At, AtBlock};
+use crate::client::{find_event, MubertClient};
+use crate::dispatch::TxOutcome;
+use crate::error::Error;
+use crate::ip_onchain_runtime;
+use crate::ip_onchain_runtime::ip_onchain::events::{AuthorAdded, AuthorEdited};
+use crate::ip_onchain_runtime::ip_onchain::storage::types::authors::Authors;
+use subxt::utils::AccountId32;
+
+pub type AuthorDetails = Authors;
+
+impl MubertClient {
+    pub async fn create_author(
+        &self,
+        nickname: &str,
+        real_name: Option<String>,
+        owner: Option<AccountId32>,
+    ) -> Result<TxOutcome<AuthorAdded>, Error> {
+        let call = ip_onchain_runtime::tx().ip_onchain().create_author(
+            nickname.to_string().into(),
+            real_name.map(Into::into),
+            owner,
+        );
+        self.submit(&call)
+            .await?
+            .and_then(|events| find_event(&events))
+    }
+
+    pub async fn edit_author(
+        &self,
+        author_id: u32,
+        real_name: Option<String>,
+        new_owner: Option<AccountId32>,
+    ) -> Result<TxOutcome<AuthorEdited>, Error> {
+        let call = ip_onchain_runtime::tx().ip_onchain().edit_author(
+            author_id,
+            real_name.map(Into::into),
+            new_owner,
+        );
+        self.submit(&call)
+            .await?
+            .and_then(|events| find_event(&events))
+    }
+
+    pub async fn get_author(
+        &self,
+        author_id: u32,
+        at: &Option<At>,
+    ) -> Result<AtBlock<AuthorDetails>, Error> {
+        let block = self.block(at).await?;
+        let query = ip_onchain_runtime::storage()
+            .ip_onchain()
+            .authors(author_id);
+
+        let details = block
+            .storage()
+            .fetch(&query)
+            .await?
+            .ok_or_else(|| Error::NotFound("author not found".into()))?;
+        Ok(AtBlock::new(&block, details))
+    }
+}
+
+pub async fn create_author(
+    client: &MubertClient,
+    nickname: &str,
+    real_name: &Option<String>,
+    owner: &Option<AccountId32>,
+) -> Result<(), Error> {
+    println!("Submitting transaction...");
+    let Some(event) = client
+        .create_author(nickname, real_name.clone(), owner.clone())
+        .await?
+        .print_unsent()?
+    else {
+        return Ok(());
+    };
+    println!("Author added successful: {:?}", event);
+    get_author(client, event.author_id, &None).await
+}
+
+pub async fn edit_author(
+    client: &MubertClient,
+    author_id: u32,
+    real_name: &Option<String>,
+    new_owner: &Option<AccountId32>,
+) -> Result<(), Error> {
+    println!("Submitting transaction...");
+    let Some(event) = client
+        .edit_author(author_id, real_name.clone(), new_owner.clone())
+        .await?
+        .print_unsent()?
+    else {
+        return Ok(());
+    };
+    println!("Author edited successful: {:?}", event);
+    get_author(client, event.author_id, &None).await
+}
+
+pub async fn get_author(
+    client: &MubertClient,
+    author_id: u32,
+    at: &Option<At>,
+) -> Result<(), Error> {
+    let author = client.get_author(author_id, at).await?;
+    println!("{}", serde_json::to_string(&author)?);
+    Ok(())
+}
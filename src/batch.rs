@@ -0,0 +1,426 @@
+use crate::client::{find_event, MubertClient};
+use crate::dispatch::TxOutcome;
+use crate::error::Error;
+use crate::ip_onchain_runtime::ip_onchain::events::EntityAdded;
+use crate::update_ip::CreateEntityFields;
+use futures::{stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use subxt::config::DefaultExtrinsicParamsBuilder;
+use subxt::utils::H256;
+use subxt::PolkadotConfig;
+
+#[derive(Deserialize)]
+struct ManifestEntry {
+    file: PathBuf,
+    #[serde(flatten)]
+    fields: CreateEntityFields,
+}
+
+/// Progress of every manifest entry, keyed by its position in the manifest.
+#[derive(Serialize, Deserialize, Default)]
+struct BatchState {
+    entries: BTreeMap<usize, EntryState>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct EntryState {
+    file: PathBuf,
+    job_id: Option<String>,
+    metadata_url: Option<String>,
+    nonce: Option<u64>,
+    tx_hash: Option<H256>,
+    entity_id: Option<u32>,
+    error: Option<String>,
+}
+
+impl EntryState {
+    /// Records the error of a submitted tx. A tx failing dispatch created no entity,
+    /// so its nonce is forgotten and a new run creates the entity again.
+    fn tx_failed(&mut self, e: Error) {
+        if let Error::Dispatch(_) = e {
+            self.nonce = None;
+            self.tx_hash = None;
+        }
+        self.error = Some(e.to_string());
+    }
+}
+
+impl BatchState {
+    fn load(path: &Path) -> Result<Self, Error> {
+        if !path.exists() {
+            return Ok(BatchState::default());
+        }
+        let data = std::fs::read_to_string(path)
+            .map_err(|e| Error::Config(format!("read state_file {:?}: {e}", path)))?;
+        serde_json::from_str(data.as_str()).map_err(|e| Error::Decode(format!("parsing json: {e}")))
+    }
+
+    fn save(&self, path: &Path) -> Result<(), Error> {
+        // write then rename, so an interrupted run never leaves a truncated state file
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, serde_json::to_string_pretty(self)?)
+            .map_err(|e| Error::Config(format!("write state_file {:?}: {e}", tmp_path)))?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    fn init(&mut self, entries: &[ManifestEntry]) -> Result<(), Error> {
+        for (i, entry) in entries.iter().enumerate() {
+            let state = self.entries.entry(i).or_insert_with(|| EntryState {
+                file: entry.file.clone(),
+                ..Default::default()
+            });
+            if state.file != entry.file {
+                return Err(Error::Config(format!(
+                    "manifest entry {i} changed from {:?} to {:?}, use a new state file",
+                    state.file, entry.file
+                )));
+            }
+            if state.metadata_url.is_none() {
+                state.metadata_url = entry.fields.metadata_url.clone();
+            }
+            state.error = None;
+        }
+        Ok(())
+    }
+
+    fn entry(&mut self, i: usize) -> &mut EntryState {
+        self.entries.entry(i).or_default()
+    }
+}
+
+fn read_manifest(manifest: &Path) -> Result<Vec<ManifestEntry>, Error> {
+    let data = std::fs::read_to_string(manifest)
+        .map_err(|e| Error::Config(format!("read manifest {:?}: {e}", manifest)))?;
+    let base_dir = manifest.parent().unwrap_or(Path::new(""));
+
+    let mut entries = match manifest.extension().and_then(|ext| ext.to_str()) {
+        Some("csv") => parse_csv_manifest(&data, base_dir)?,
+        _ => serde_json::from_str::<Vec<ManifestEntry>>(data.as_str())
+            .map_err(|e| Error::Decode(format!("parsing json: {e}")))?,
+    };
+
+    for entry in entries.iter_mut() {
+        entry.file = base_dir.join(&entry.file);
+    }
+    Ok(entries)
+}
+
+/// Csv manifest lines are `file,data_file` where `data_file` holds the entity fields json.
+fn parse_csv_manifest(data: &str, base_dir: &Path) -> Result<Vec<ManifestEntry>, Error> {
+    let mut entries = Vec::new();
+    for (n, line) in data.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || (n == 0 && line == "file,data_file") {
+            continue;
+        }
+        let (file, data_file) = line.split_once(',').ok_or(Error::Config(format!(
+            "manifest line {}: expected file,data_file",
+            n + 1
+        )))?;
+
+        let data_file = base_dir.join(data_file.trim());
+        let fields = std::fs::read_to_string(&data_file)
+            .map_err(|e| Error::Config(format!("read data_file {:?}: {e}", data_file)))?;
+        let fields: CreateEntityFields = serde_json::from_str(fields.as_str())
+            .map_err(|e| Error::Decode(format!("parsing json: {e}")))?;
+
+        entries.push(ManifestEntry {
+            file: PathBuf::from(file.trim()),
+            fields,
+        });
+    }
+    Ok(entries)
+}
+
+pub async fn batch_upload(
+    client: &MubertClient,
+    manifest: &Path,
+    state_file: &Option<PathBuf>,
+    workers: usize,
+) -> Result<(), Error> {
+    let (api, signer) = (client.api(), client.signer()?);
+    let entries = read_manifest(manifest)?;
+    let entries_ref = &entries;
+
+    let state_path = state_file
+        .clone()
+        .unwrap_or_else(|| manifest.with_extension("state.json"));
+    let mut state = BatchState::load(&state_path)?;
+    state.init(&entries)?;
+    state.save(&state_path)?;
+
+    for entry in entries.iter() {
+        entry.fields.check_nft_collection(api).await?;
+    }
+
+    // upload audio files which have no fingerprint job yet
+    let to_upload: Vec<usize> = state
+        .entries
+        .iter()
+        .filter(|(_, s)| s.entity_id.is_none() && s.metadata_url.is_none() && s.job_id.is_none())
+        .map(|(i, _)| *i)
+        .collect();
+
+    let mut uploads = stream::iter(to_upload)
+        .map(|i| async move {
+            (
+                i,
+                client
+                    .fingerprint_api()
+                    .upload_audio(&entries_ref[i].file)
+                    .await,
+            )
+        })
+        .buffer_unordered(workers.max(1));
+
+    while let Some((i, result)) = uploads.next().await {
+        match result {
+            Ok(job) => {
+                println!(
+                    "{:?}: fingerprint worker job id: {}",
+                    entries[i].file, job.id
+                );
+                state.entry(i).job_id = Some(job.id);
+            }
+            Err(e) => state.entry(i).error = Some(format!("upload_audio_fingerprint: {e}")),
+        }
+        state.save(&state_path)?;
+    }
+    drop(uploads);
+
+    // wait for fingerprints and create off chain metadata
+    let to_fingerprint: Vec<(usize, String)> = state
+        .entries
+        .iter()
+        .filter(|(_, s)| s.entity_id.is_none() && s.metadata_url.is_none())
+        .filter_map(|(i, s)| s.job_id.clone().map(|job_id| (*i, job_id)))
+        .collect();
+
+    let mut fingerprints = stream::iter(to_fingerprint)
+        .map(|(i, job_id)| async move {
+            let result = async {
+                let fingerprint = client
+                    .fingerprint_api()
+                    .wait_for_fingerprint_url(&job_id)
+                    .await?;
+                let metadata_req = entries_ref[i].fields.metadata_request(fingerprint);
+                let off_chain_metadata = client
+                    .fingerprint_api()
+                    .create_metadata(&metadata_req)
+                    .await?;
+                Ok::<String, Error>(off_chain_metadata.url)
+            };
+            (i, result.await)
+        })
+        .buffer_unordered(workers.max(1));
+
+    while let Some((i, result)) = fingerprints.next().await {
+        match result {
+            Ok(url) => {
+                println!("{:?}: off chain metadata url: {url}", entries[i].file);
+                state.entry(i).metadata_url = Some(url);
+            }
+            // the job ids are kept in the state, a new run waits for them again
+            Err(e @ Error::Cancelled(_)) => return Err(e),
+            // a new run uploads the audio again
+            Err(e @ Error::JobFailed(_)) => {
+                let entry_state = state.entry(i);
+                entry_state.job_id = None;
+                entry_state.error = Some(e.to_string());
+            }
+            Err(e) => state.entry(i).error = Some(e.to_string()),
+        }
+        state.save(&state_path)?;
+    }
+    drop(fingerprints);
+
+    // a recorded nonce below the account nonce means the tx was included before the
+    // previous run stopped, the entity may exist already and must not be created twice
+    let account_id = signer.account_id();
+    let mut nonce = api.tx().account_nonce(&account_id).await?;
+
+    for entry_state in state.entries.values_mut() {
+        match entry_state.nonce {
+            Some(tx_nonce) if entry_state.entity_id.is_none() && tx_nonce < nonce => {
+                entry_state.error = Some(format!(
+                    "tx {:?} was included, but entity id unknown: check the chain and remove \
+                     the entry from the state file to retry",
+                    entry_state.tx_hash
+                ));
+            }
+            Some(_) if entry_state.entity_id.is_none() => {
+                entry_state.nonce = None;
+                entry_state.tx_hash = None;
+            }
+            _ => {}
+        }
+    }
+    state.save(&state_path)?;
+
+    println!("Submitting transactions...");
+    let mut submitted = Vec::new();
+    for (i, entry) in entries.into_iter().enumerate() {
+        let entry_state = state.entry(i);
+        if entry_state.entity_id.is_some() || entry_state.error.is_some() {
+            continue;
+        }
+        let Some(metadata_url) = entry_state.metadata_url.clone() else {
+            continue;
+        };
+
+        let call = match entry.fields.into_call(metadata_url) {
+            Ok(call) => call,
+            Err(e) => {
+                entry_state.error = Some(e.to_string());
+                state.save(&state_path)?;
+                continue;
+            }
+        };
+        let params = DefaultExtrinsicParamsBuilder::<PolkadotConfig>::new()
+            .nonce(nonce)
+            .build();
+
+        let tx = match signer
+            .create_signed(api, &call, params)
+            .await
+            .and_then(TxOutcome::into_result)
+        {
+            Ok(tx) => tx,
+            Err(e) => {
+                entry_state.error = Some(format!("can not sign tx: {e}"));
+                state.save(&state_path)?;
+                continue;
+            }
+        };
+
+        // remember the tx before sending it, so a crash right after can be detected
+        entry_state.nonce = Some(nonce);
+        entry_state.tx_hash = Some(tx.hash());
+        state.save(&state_path)?;
+
+        match tx.submit_and_watch().await {
+            Ok(tx_progress) => {
+                nonce += 1;
+                submitted.push((i, tx_progress));
+            }
+            Err(e) => {
+                let entry_state = state.entry(i);
+                entry_state.nonce = None;
+                entry_state.tx_hash = None;
+                entry_state.error = Some(format!("can not submit tx: {e}"));
+                state.save(&state_path)?;
+            }
+        }
+    }
+
+    println!("wait finalization...");
+    for (i, tx_progress) in submitted {
+        let result = async {
+            let events = client.wait_events(tx_progress).await?;
+            find_event::<EntityAdded>(&events)
+        };
+
+        match result.await {
+            Ok(event) => {
+                println!("Entity added successful: {:?}", event);
+                state.entry(i).entity_id = Some(event.entity_id);
+            }
+            Err(e) => state.entry(i).tx_failed(e),
+        }
+        state.save(&state_path)?;
+    }
+
+    let mut failed = 0;
+    for (i, entry_state) in state.entries.iter() {
+        match (&entry_state.entity_id, &entry_state.error) {
+            (Some(entity_id), _) => println!("{i} {:?}: entity_id={entity_id}", entry_state.file),
+            (None, Some(error)) => {
+                failed += 1;
+                println!("{i} {:?}: failed: {error}", entry_state.file);
+            }
+            (None, None) => {
+                failed += 1;
+                println!("{i} {:?}: not done", entry_state.file);
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(Error::Config(format!(
+            "{failed} entries not done, re-run with the same state file {:?} to resume",
+            state_path
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dispatch::TxFailed;
+
+    #[test]
+    fn test_parse_csv_manifest() {
+        let dir = std::env::temp_dir().join("mubert_cli_test_parse_csv_manifest");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::copy("examples/create_entity.json", dir.join("track.json")).unwrap();
+
+        let entries = parse_csv_manifest(
+            "file,data_file\na.wav, track.json\n\nb.wav,track.json\n",
+            &dir,
+        )
+        .unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].file, PathBuf::from("a.wav"));
+        assert_eq!(entries[1].file, PathBuf::from("b.wav"));
+
+        assert!(parse_csv_manifest("a.wav", &dir).is_err());
+    }
+
+    #[test]
+    fn test_state_init_detects_changed_manifest() {
+        let entries: Vec<ManifestEntry> = serde_json::from_str(
+            r#"[{"file": "a.wav", "entity_kind": "Track", "authority_id": 0,
+                 "metadata_standard": "M25", "flags": [],
+                 "off_chain_metadata": {"title": "a", "bpm": 120, "key": 1, "scale": 0, "instrument": 1},
+                 "metadata_url": "url_a"}]"#,
+        )
+        .unwrap();
+
+        let mut state = BatchState::default();
+        state.init(&entries).unwrap();
+        assert_eq!(state.entries[&0].metadata_url.as_deref(), Some("url_a"));
+
+        state.entries.get_mut(&0).unwrap().file = PathBuf::from("b.wav");
+        assert!(state.init(&entries).is_err());
+    }
+
+    #[test]
+    fn test_entry_tx_failed() {
+        let submitted = || EntryState {
+            nonce: Some(7),
+            tx_hash: Some(H256::repeat_byte(1)),
+            ..Default::default()
+        };
+
+        let mut entry = submitted();
+        entry.tx_failed(Error::Dispatch(TxFailed {
+            code: "IPOnchain::AuthorityNotFound".into(),
+            docs: String::new(),
+        }));
+        assert_eq!((entry.nonce, entry.tx_hash), (None, None));
+        assert_eq!(
+            entry.error.as_deref(),
+            Some("tx failed: IPOnchain::AuthorityNotFound")
+        );
+
+        // a tx not known to be failed may have created the entity, its nonce is kept
+        let mut entry = submitted();
+        entry.tx_failed(Error::Timeout("tx finalization".into()));
+        assert_eq!(entry.nonce, Some(7));
+        assert!(entry.tx_hash.is_some());
+    }
+}
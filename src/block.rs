@@ -0,0 +1,103 @@
+use crate::error::Error;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::str::FromStr;
+use subxt::ext::codec::Decode;
+use subxt::utils::{AccountId32, MultiAddress, H256};
+use subxt::{OnlineClient, PolkadotConfig};
+
+pub type Block = subxt::blocks::Block<PolkadotConfig, OnlineClient<PolkadotConfig>>;
+
+/// Block to query the state at, given as a block hash or a block number.
+#[derive(Clone, Debug, PartialEq)]
+pub enum At {
+    Hash(H256),
+    Number(u32),
+}
+
+impl FromStr for At {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.starts_with("0x") {
+            H256::from_str(s)
+                .map(At::Hash)
+                .map_err(|e| format!("bad block hash {s}: {e}"))
+        } else {
+            s.parse::<u32>()
+                .map(At::Number)
+                .map_err(|e| format!("bad block number {s}: {e}"))
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct BlockInfo {
+    pub block_number: u32,
+    pub block_hash: H256,
+}
+
+impl From<&Block> for BlockInfo {
+    fn from(block: &Block) -> Self {
+        BlockInfo {
+            block_number: block.number(),
+            block_hash: block.hash(),
+        }
+    }
+}
+
+/// Query result together with the block it was read at.
+#[derive(Serialize)]
+pub struct AtBlock<T> {
+    #[serde(flatten)]
+    pub block: BlockInfo,
+    #[serde(flatten)]
+    pub details: T,
+}
+
+impl<T> AtBlock<T> {
+    pub fn new(block: &Block, details: T) -> Self {
+        AtBlock {
+            block: block.into(),
+            details,
+        }
+    }
+}
+
+/// Signers of the block extrinsics, by extrinsic index.
+pub(crate) async fn extrinsic_signers(block: &Block) -> Result<HashMap<u32, AccountId32>, Error> {
+    let extrinsics = block.extrinsics().await?;
+    let signers = extrinsics
+        .iter()
+        .filter_map(|ext| {
+            let bytes = ext.address_bytes()?;
+            match MultiAddress::<AccountId32, ()>::decode(&mut &bytes[..]).ok()? {
+                MultiAddress::Id(account_id) => Some((ext.index(), account_id)),
+                _ => None,
+            }
+        })
+        .collect();
+    Ok(signers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_number() {
+        assert_eq!(At::from_str("42"), Ok(At::Number(42)));
+    }
+
+    #[test]
+    fn test_parse_hash() {
+        let hash = "0x0101010101010101010101010101010101010101010101010101010101010101";
+        assert_eq!(At::from_str(hash), Ok(At::Hash(H256::repeat_byte(1))));
+    }
+
+    #[test]
+    fn test_parse_bad() {
+        assert!(At::from_str("latest").is_err());
+        assert!(At::from_str("0x01").is_err());
+    }
+}
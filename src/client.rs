@@ -0,0 +1,239 @@
+use crate::api::FingerprintApi;
+use crate::block::{At, Block};
+use crate::dispatch::{events_error, TxOutcome};
+use crate::error::Error;
+use crate::poll::PollOptions;
+use crate::signer::{Progress, Signer};
+use crate::upload::UploadOptions;
+use std::time::Duration;
+use subxt::backend::legacy::LegacyRpcMethods;
+use subxt::backend::rpc::RpcClient;
+use subxt::blocks::ExtrinsicEvents;
+use subxt::events::StaticEvent;
+use subxt::tx::Payload;
+use subxt::{OnlineClient, PolkadotConfig};
+
+pub const DEFAULT_NODE_URL: &str = "ws://127.0.0.1:9944";
+pub const DEFAULT_API_URL: &str = "https://fingerprint.mubert.xyz";
+
+pub type Events = ExtrinsicEvents<PolkadotConfig>;
+
+/// Connections to the node and the fingerprint api, made once and shared by all the calls.
+///
+/// The domain modules add their methods, like `get_entity` in `update_ip` or
+/// `create_authority` in `create_authority`, the off chain calls are in `fingerprint_api()`.
+/// Nothing is printed, the cli does it.
+pub struct MubertClient {
+    api: OnlineClient<PolkadotConfig>,
+    rpc: LegacyRpcMethods<PolkadotConfig>,
+    fingerprint_api: FingerprintApi,
+    arweave_poll: PollOptions,
+    signer: Option<Signer>,
+    tx_timeout: Duration,
+}
+
+#[derive(Default)]
+pub struct MubertClientBuilder {
+    node_url: Option<String>,
+    api_url: Option<String>,
+    auth_token: Option<String>,
+    signer: Option<Signer>,
+    request_timeout: Option<Duration>,
+    fingerprint_poll: Option<PollOptions>,
+    arweave_poll: Option<PollOptions>,
+    upload: Option<UploadOptions>,
+    tx_timeout: Option<Duration>,
+}
+
+impl MubertClientBuilder {
+    /// Websocket url of the node, `ws://127.0.0.1:9944` by default.
+    pub fn node_url(mut self, node_url: impl Into<String>) -> Self {
+        self.node_url = Some(node_url.into());
+        self
+    }
+
+    /// Base url of the fingerprint api, `https://fingerprint.mubert.xyz` by default.
+    pub fn api_url(mut self, api_url: impl Into<String>) -> Self {
+        self.api_url = Some(api_url.into());
+        self
+    }
+
+    /// Bearer token of the fingerprint api.
+    pub fn auth_token(mut self, auth_token: impl Into<String>) -> Self {
+        self.auth_token = Some(auth_token.into());
+        self
+    }
+
+    /// Signer of the transactions, only queries can be made without it.
+    pub fn signer(mut self, signer: Signer) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    /// Timeout of every fingerprint api request, 60s by default.
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = Some(timeout);
+        self
+    }
+
+    /// Polling of the fingerprint status, from every 10s for 30 min by default.
+    pub fn fingerprint_poll(mut self, poll: PollOptions) -> Self {
+        self.fingerprint_poll = Some(poll);
+        self
+    }
+
+    /// Polling of the arweave task, from every 6s for 30 min by default.
+    pub fn arweave_poll(mut self, poll: PollOptions) -> Self {
+        self.arweave_poll = Some(poll);
+        self
+    }
+
+    /// Chunk size, retries and job cache of the audio uploads.
+    pub fn upload_options(mut self, upload: UploadOptions) -> Self {
+        self.upload = Some(upload);
+        self
+    }
+
+    /// How long a submitted transaction may take to be finalized, 5 min by default.
+    pub fn tx_timeout(mut self, timeout: Duration) -> Self {
+        self.tx_timeout = Some(timeout);
+        self
+    }
+
+    pub async fn build(self) -> Result<MubertClient, Error> {
+        let node_url = self.node_url.as_deref().unwrap_or(DEFAULT_NODE_URL);
+        let rpc_client = RpcClient::from_url(node_url)
+            .await
+            .map_err(|e| Error::Rpc(format!("chain rpc api: {e}")))?;
+        let api = OnlineClient::<PolkadotConfig>::from_rpc_client(rpc_client.clone())
+            .await
+            .map_err(|e| Error::Rpc(format!("chain rpc api: {e}")))?;
+
+        let mut fingerprint_api = FingerprintApi::new(
+            self.api_url.as_deref().unwrap_or(DEFAULT_API_URL),
+            self.auth_token,
+            self.request_timeout.unwrap_or(Duration::from_secs(60)),
+        )?;
+        if let Some(poll) = self.fingerprint_poll {
+            fingerprint_api = fingerprint_api.with_poll_options(poll);
+        }
+        if let Some(upload) = self.upload {
+            fingerprint_api = fingerprint_api.with_upload_options(upload);
+        }
+
+        Ok(MubertClient {
+            api,
+            rpc: LegacyRpcMethods::new(rpc_client),
+            fingerprint_api,
+            arweave_poll: self
+                .arweave_poll
+                .unwrap_or(PollOptions::every(Duration::from_secs(6))),
+            signer: self.signer,
+            tx_timeout: self.tx_timeout.unwrap_or(Duration::from_secs(300)),
+        })
+    }
+}
+
+impl MubertClient {
+    pub fn builder() -> MubertClientBuilder {
+        MubertClientBuilder::default()
+    }
+
+    pub fn api(&self) -> &OnlineClient<PolkadotConfig> {
+        &self.api
+    }
+
+    pub fn rpc(&self) -> &LegacyRpcMethods<PolkadotConfig> {
+        &self.rpc
+    }
+
+    pub fn signer(&self) -> Result<&Signer, Error> {
+        self.signer
+            .as_ref()
+            .ok_or_else(|| Error::Key("no signer given to the client".into()))
+    }
+
+    pub fn fingerprint_api(&self) -> &FingerprintApi {
+        &self.fingerprint_api
+    }
+
+    pub(crate) fn tx_timeout(&self) -> Duration {
+        self.tx_timeout
+    }
+
+    pub(crate) fn arweave_poll(&self) -> &PollOptions {
+        &self.arweave_poll
+    }
+
+    /// Block to query the state at, the latest one by default.
+    pub async fn block(&self, at: &Option<At>) -> Result<Block, Error> {
+        let block = match at {
+            None => self.api.blocks().at_latest().await?,
+            Some(At::Hash(hash)) => self.api.blocks().at(*hash).await?,
+            Some(At::Number(number)) => {
+                let hash = self
+                    .rpc
+                    .chain_get_block_hash(Some((*number).into()))
+                    .await?
+                    .ok_or(Error::NotFound(format!("block {number} not found")))?;
+                self.api.blocks().at(hash).await?
+            }
+        };
+        Ok(block)
+    }
+
+    /// Signs and submits the call, then waits for its finalized events. With `tx build`,
+    /// `--dry-run` or a multisig still needing approvals the outcome has no events.
+    pub async fn submit<Call: Payload>(&self, call: &Call) -> Result<TxOutcome<Events>, Error> {
+        self.signer()?
+            .submit(&self.api, call, self.tx_timeout)
+            .await
+    }
+
+    /// Waits for the finalized tx, see `wait_events`.
+    pub async fn wait_events(&self, progress: Progress) -> Result<Events, Error> {
+        wait_events(progress, self.tx_timeout).await
+    }
+}
+
+/// Waits for the finalized tx, a failed dispatch is returned as `Error::Dispatch`.
+pub(crate) async fn wait_events(progress: Progress, timeout: Duration) -> Result<Events, Error> {
+    let finalized = tokio::time::timeout(timeout, progress.wait_for_finalized())
+        .await
+        .map_err(|_| {
+            Error::Timeout(format!(
+                "tx submitted, but not finalized in {}s",
+                timeout.as_secs()
+            ))
+        })?
+        .map_err(|e| Error::Rpc(format!("tx submitted, but not finalized: {e}")))?;
+    finalized.wait_for_success().await.map_err(events_error)
+}
+
+/// First event of the kind, a successful tx without it is `Error::NotFound`.
+pub(crate) fn find_event<E: StaticEvent>(events: &Events) -> Result<E, Error> {
+    events
+        .find_first::<E>()
+        .map_err(|e| Error::Decode(format!("tx submitted, but can not decode event: {e}")))?
+        .ok_or_else(|| {
+            Error::NotFound(format!(
+                "tx submitted, but {}.{} event not found",
+                E::PALLET,
+                E::EVENT
+            ))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_build_no_node() {
+        let result = MubertClient::builder()
+            .node_url("ws://127.0.0.1:1")
+            .build()
+            .await;
+        assert!(matches!(result, Err(Error::Rpc(_))));
+    }
+}
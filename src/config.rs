@@ -0,0 +1,94 @@
+use crate::error::Error;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// Settings read from the config file, the flags and env variables override them.
+#[derive(Deserialize, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Base url of the fingerprint api.
+    pub api_url: Option<String>,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Config, Error> {
+        let data = std::fs::read_to_string(path)
+            .map_err(|e| Error::Config(format!("read config {path:?}: {e}")))?;
+        serde_json::from_str(&data)
+            .map_err(|e| Error::Config(format!("parsing config {path:?}: {e}")))
+    }
+}
+
+#[derive(clap::Args, Clone, Debug, Default)]
+pub struct ConfigArgs {
+    #[arg(
+        long,
+        global = true,
+        env = "MUBERT_API_URL",
+        help = "fingerprint api base url, https://fingerprint.mubert.xyz by default"
+    )]
+    pub api_url: Option<String>,
+    #[arg(
+        long,
+        global = true,
+        env = "MUBERT_CONFIG",
+        help = "json config file, <config dir>/mubert-cli/config.json by default"
+    )]
+    pub config: Option<PathBuf>,
+}
+
+impl ConfigArgs {
+    /// The given config file must exist, the default one is optional.
+    pub fn config(&self) -> Result<Config, Error> {
+        match &self.config {
+            Some(path) => Config::load(path),
+            None => match dirs::config_dir() {
+                Some(dir) if dir.join("mubert-cli").join("config.json").exists() => {
+                    Config::load(&dir.join("mubert-cli").join("config.json"))
+                }
+                _ => Ok(Config::default()),
+            },
+        }
+    }
+
+    /// Fingerprint api url of the flag or env, then of the config file.
+    pub fn api_url(&self) -> Result<Option<String>, Error> {
+        match &self.api_url {
+            Some(api_url) => Ok(Some(api_url.clone())),
+            None => Ok(self.config()?.api_url),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_url() {
+        let path = std::env::temp_dir().join(format!("mubert-config-{}.json", std::process::id()));
+        std::fs::write(&path, r#"{"api_url": "http://staging:8080"}"#).unwrap();
+
+        let mut args = ConfigArgs {
+            api_url: None,
+            config: Some(path.clone()),
+        };
+        assert_eq!(
+            args.api_url().unwrap().as_deref(),
+            Some("http://staging:8080")
+        );
+
+        args.api_url = Some("http://127.0.0.1:8080".into());
+        assert_eq!(
+            args.api_url().unwrap().as_deref(),
+            Some("http://127.0.0.1:8080")
+        );
+
+        std::fs::write(&path, r#"{"api_uri": "http://staging:8080"}"#).unwrap();
+        args.api_url = None;
+        assert!(matches!(args.api_url(), Err(Error::Config(_))));
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(args.api_url(), Err(Error::Config(_))));
+    }
+}
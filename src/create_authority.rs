@@ -1,87 +1,199 @@
+use crate::block::{At, AtBlock};
+use crate::client::{find_event, MubertClient};
+use crate::dispatch::TxOutcome;
+use crate::error::Error;
 use crate::ip_onchain_runtime::ip_onchain::calls::types::create_authority;
-use crate::{ip_onchain_runtime, SecretKeyFile};
-use std::error::Error;
+use crate::ip_onchain_runtime::ip_onchain::events::{AuthorityAdded, AuthorityEdited};
+use crate::ip_onchain_runtime::ip_onchain::storage::types::authorities::Authorities;
+use crate::ip_onchain_runtime::runtime_types::pallet_nfts::types::{
+    BitFlags, CollectionConfig, CollectionSetting, ItemSetting, MintSettings, MintType,
+};
+use crate::{ip_onchain_runtime, parse_flags};
+use serde::{Deserialize, Serialize};
 
 use std::path::PathBuf;
-use subxt::{OnlineClient, PolkadotConfig};
-use subxt_signer::bip39::Mnemonic;
-use subxt_signer::sr25519::{dev, Keypair};
 
-pub async fn create_authority(
-    node_url: &String,
-    name: &String,
-    kind: create_authority::AuthorityKind,
-    secret_key_file: &Option<PathBuf>,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let call =
-        ip_onchain_runtime::tx()
-            .ip_onchain()
-            .create_authority(name.clone().into(), kind, None);
+pub type AuthorityDetails = Authorities;
 
-    let mut sender_keypair = dev::alice();
+#[derive(Serialize, Deserialize)]
+struct CollectionConfigFields {
+    #[serde(default)]
+    settings: Vec<String>,
+    max_supply: Option<u32>,
+    mint_settings: MintSettingsFields,
+}
 
-    if let Some(secret_key_file) = secret_key_file {
-        let secret_key_data = std::fs::read_to_string(secret_key_file)
-            .map_err(|e| format!("read secret_key_file {:?}: {e}", secret_key_file))?;
-        let secret_key: SecretKeyFile = serde_json::from_str(secret_key_data.as_str())
-            .map_err(|e| format!("parsing json: {e}"))?;
-        let mnemonic = Mnemonic::parse(secret_key.secret_phrase).unwrap();
-        sender_keypair = Keypair::from_phrase(&mnemonic, None).unwrap();
-    }
+#[derive(Serialize, Deserialize)]
+struct MintSettingsFields {
+    mint_type: MintType<u64>,
+    price: Option<u128>,
+    start_block: Option<u32>,
+    end_block: Option<u32>,
+    #[serde(default)]
+    default_item_settings: Vec<String>,
+}
 
-    let api = OnlineClient::<PolkadotConfig>::from_url(node_url)
-        .await
-        .map_err(|e| format!("chain rpc api: {e}"))?;
+/// The listed `settings` and `default_item_settings` are disabled, like the set bits of
+/// pallet_nfts: `TransferableItems` makes the items non transferable.
+impl TryFrom<CollectionConfigFields> for create_authority::CollectionCfg {
+    type Error = Error;
 
-    println!("Submitting transaction...");
-    let tx_progress = api
-        .tx()
-        .sign_and_submit_then_watch_default(&call, &sender_keypair)
-        .await
-        .map_err(|e| format!("can not submit tx: {e}"))?;
-
-    println!("wait finalization...");
-    let finalized = tx_progress
-        .wait_for_finalized()
-        .await
-        .map_err(|e| format!("tx submitted, but not finalize: {e}"))?;
-
-    println!("wait events...");
-    let events = finalized
-        .fetch_events()
-        .await
-        .map_err(|e| format!("tx submitted, but not can not fetch events: {e}"))?;
-
-    // check events
-    if let Some(event) = events
-        .find_first::<ip_onchain_runtime::ip_onchain::events::AuthorityAdded>()
-        .map_err(|e| format!("tx submitted, but event not found: {e}"))?
-    {
-        println!("Authority added successful: {:?}", event);
+    fn try_from(item: CollectionConfigFields) -> Result<Self, Error> {
+        Ok(Some(CollectionConfig {
+            settings: BitFlags(
+                parse_flags::<CollectionSetting>(&item.settings)?,
+                Default::default(),
+            ),
+            max_supply: item.max_supply,
+            mint_settings: MintSettings {
+                mint_type: item.mint_settings.mint_type,
+                price: item.mint_settings.price,
+                start_block: item.mint_settings.start_block,
+                end_block: item.mint_settings.end_block,
+                default_item_settings: BitFlags(
+                    parse_flags::<ItemSetting>(&item.mint_settings.default_item_settings)?,
+                    Default::default(),
+                ),
+            },
+        }))
     }
-    Ok(())
 }
 
-pub async fn get_authority(node_url: &String, authority_id: u32) -> Result<(), Box<dyn Error>> {
-    let api = OnlineClient::<PolkadotConfig>::from_url(node_url)
-        .await
-        .map_err(|e| format!("chain rpc api: {e}"))?;
+fn read_collection_cfg(
+    collection_cfg_file: &Option<PathBuf>,
+) -> Result<create_authority::CollectionCfg, Error> {
+    let Some(collection_cfg_file) = collection_cfg_file else {
+        return Ok(None);
+    };
 
-    let query = ip_onchain_runtime::storage()
-        .ip_onchain()
-        .authorities(authority_id);
+    let data = std::fs::read_to_string(collection_cfg_file).map_err(|e| {
+        Error::Config(format!(
+            "read collection_cfg_file {:?}: {e}",
+            collection_cfg_file
+        ))
+    })?;
+    let fields: CollectionConfigFields = serde_json::from_str(data.as_str())
+        .map_err(|e| Error::Decode(format!("parsing json: {e}")))?;
 
-    let details = api
-        .storage()
-        .at_latest()
-        .await?
-        .fetch(&query)
+    fields.try_into()
+}
+
+impl MubertClient {
+    pub async fn create_authority(
+        &self,
+        name: &str,
+        kind: create_authority::AuthorityKind,
+        collection_cfg: create_authority::CollectionCfg,
+    ) -> Result<TxOutcome<AuthorityAdded>, Error> {
+        let call = ip_onchain_runtime::tx().ip_onchain().create_authority(
+            name.to_string().into(),
+            kind,
+            collection_cfg,
+        );
+        self.submit(&call)
+            .await?
+            .and_then(|events| find_event(&events))
+    }
+
+    pub async fn edit_authority(
+        &self,
+        authority_id: u32,
+        name: Option<String>,
+        kind: Option<create_authority::AuthorityKind>,
+        collection_cfg: create_authority::CollectionCfg,
+    ) -> Result<TxOutcome<AuthorityEdited>, Error> {
+        let call = ip_onchain_runtime::tx().ip_onchain().edit_authority(
+            authority_id,
+            name.map(Into::into),
+            kind,
+            collection_cfg,
+        );
+        self.submit(&call)
+            .await?
+            .and_then(|events| find_event(&events))
+    }
+
+    pub async fn get_authority(
+        &self,
+        authority_id: u32,
+        at: &Option<At>,
+    ) -> Result<AtBlock<AuthorityDetails>, Error> {
+        let block = self.block(at).await?;
+        let query = ip_onchain_runtime::storage()
+            .ip_onchain()
+            .authorities(authority_id);
+
+        let details = block
+            .storage()
+            .fetch(&query)
+            .await?
+            .ok_or_else(|| Error::NotFound("authority not found".into()))?;
+        Ok(AtBlock::new(&block, details))
+    }
+}
+
+pub async fn create_authority(
+    client: &MubertClient,
+    name: &str,
+    kind: create_authority::AuthorityKind,
+    collection_cfg_file: &Option<PathBuf>,
+) -> Result<(), Error> {
+    let collection_cfg = read_collection_cfg(collection_cfg_file)?;
+
+    println!("Submitting transaction...");
+    let Some(event) = client
+        .create_authority(name, kind, collection_cfg)
         .await?
-        .ok_or("authority not found")?;
+        .print_unsent()?
+    else {
+        return Ok(());
+    };
+    println!("Authority added successful: {:?}", event);
+    Ok(())
+}
 
-    let data = serde_json::to_string(&details).unwrap();
+pub async fn edit_authority(
+    client: &MubertClient,
+    authority_id: u32,
+    name: &Option<String>,
+    kind: Option<create_authority::AuthorityKind>,
+    collection_cfg_file: &Option<PathBuf>,
+) -> Result<(), Error> {
+    let collection_cfg = read_collection_cfg(collection_cfg_file)?;
 
-    println!("{data}");
+    println!("Submitting transaction...");
+    let Some(event) = client
+        .edit_authority(authority_id, name.clone(), kind, collection_cfg)
+        .await?
+        .print_unsent()?
+    else {
+        return Ok(());
+    };
+    println!("Authority edited successful: {:?}", event);
+    get_authority(client, event.authority_id, &None).await
+}
 
+pub async fn get_authority(
+    client: &MubertClient,
+    authority_id: u32,
+    at: &Option<At>,
+) -> Result<(), Error> {
+    let authority = client.get_authority(authority_id, at).await?;
+    println!("{}", serde_json::to_string(&authority)?);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_example_collection_config() {
+        let path =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("examples/collection_config.json");
+        let config = read_collection_cfg(&Some(path)).unwrap().unwrap();
+        // nothing disabled: transferable items, unlocked metadata and attributes
+        assert_eq!(config.settings.0, 0);
+        assert_eq!(config.mint_settings.default_item_settings.0, 0);
+    }
+}
@@ -0,0 +1,188 @@
+use crate::dry_run::DryRun;
+use crate::error::Error;
+use crate::multisig::MultisigPending;
+use std::fmt;
+use std::path::PathBuf;
+use subxt::error::DispatchError;
+
+/// Exit code of a transaction that failed on chain, other errors exit with 1.
+pub const TX_FAILED_EXIT_CODE: i32 = 2;
+
+/// Transaction failed on chain, or would fail with `--dry-run`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TxFailed {
+    /// Stable code for scripts, like `IPOnchain::AuthorityNotFound`, `BadOrigin`
+    /// or `InvalidTransaction::Payment`.
+    pub code: String,
+    pub docs: String,
+}
+
+impl fmt::Display for TxFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.docs.as_str() {
+            "" => write!(f, "tx failed: {}", self.code),
+            docs => write!(f, "tx failed: {}: {docs}", self.code),
+        }
+    }
+}
+
+impl std::error::Error for TxFailed {}
+
+impl From<&DispatchError> for TxFailed {
+    fn from(error: &DispatchError) -> Self {
+        if let DispatchError::Module(module_error) = error {
+            if let Ok(details) = module_error.details() {
+                let docs = details.variant.docs.join(" ");
+                return TxFailed {
+                    code: format!("{}::{}", details.pallet.name(), details.variant.name),
+                    docs: docs.trim().to_string(),
+                };
+            }
+        }
+        // `Token(FundsUnavailable)` -> `Token::FundsUnavailable`
+        let code = format!("{error:?}").replace('(', "::").replace(')', "");
+        TxFailed {
+            code,
+            docs: error.to_string(),
+        }
+    }
+}
+
+/// What became of a transaction. Only `Submitted` went on chain, the others are the
+/// command stopping on purpose before, they are not errors.
+#[derive(Debug)]
+pub enum TxOutcome<T> {
+    /// Signed, submitted or finalized depending on the step, with its result.
+    Submitted(T),
+    /// `tx build` wrote the unsigned transaction to this file.
+    Built(PathBuf),
+    /// The multisig call needs more approvals.
+    Pending(MultisigPending),
+    /// `--dry-run` result, nothing is broadcast.
+    DryRun(DryRun),
+}
+
+impl<T> TxOutcome<T> {
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> TxOutcome<U> {
+        match self.into_submitted() {
+            Ok(value) => TxOutcome::Submitted(f(value)),
+            Err(outcome) => outcome,
+        }
+    }
+
+    pub fn and_then<U>(self, f: impl FnOnce(T) -> Result<U, Error>) -> Result<TxOutcome<U>, Error> {
+        match self.into_submitted() {
+            Ok(value) => Ok(TxOutcome::Submitted(f(value)?)),
+            Err(outcome) => Ok(outcome),
+        }
+    }
+
+    /// The submitted value, or the outcome without one to pass on.
+    pub fn into_submitted<U>(self) -> Result<T, TxOutcome<U>> {
+        match self {
+            TxOutcome::Submitted(value) => Ok(value),
+            TxOutcome::Built(path) => Err(TxOutcome::Built(path)),
+            TxOutcome::Pending(pending) => Err(TxOutcome::Pending(pending)),
+            TxOutcome::DryRun(dry_run) => Err(TxOutcome::DryRun(dry_run)),
+        }
+    }
+
+    /// For the callers that need the tx on chain, like the worker: the other outcomes
+    /// are a `Config` error.
+    pub fn into_result(self) -> Result<T, Error> {
+        self.into_submitted::<()>()
+            .map_err(|outcome| Error::Config(format!("tx not sent: {outcome}")))
+    }
+
+    /// For the cli: prints the outcomes that did not go on chain. A dry run predicting
+    /// a failure is `Error::Dispatch`, to exit like the failed tx.
+    pub fn print_unsent(self) -> Result<Option<T>, Error> {
+        match self.into_submitted::<()>() {
+            Ok(value) => Ok(Some(value)),
+            Err(outcome) => {
+                println!("{outcome}");
+                match outcome {
+                    TxOutcome::DryRun(DryRun {
+                        result: Err(failed),
+                        ..
+                    }) => Err(Error::Dispatch(failed)),
+                    _ => Ok(None),
+                }
+            }
+        }
+    }
+}
+
+impl<T> fmt::Display for TxOutcome<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TxOutcome::Submitted(_) => write!(f, "tx submitted"),
+            TxOutcome::Built(path) => write!(f, "unsigned tx written to {}", path.display()),
+            TxOutcome::Pending(pending) => write!(f, "{pending}"),
+            TxOutcome::DryRun(dry_run) => write!(f, "{dry_run}"),
+        }
+    }
+}
+
+/// Error of waiting for the tx events, `ExtrinsicFailed` is decoded to `TxFailed`.
+pub(crate) fn events_error(e: subxt::Error) -> Error {
+    match e {
+        subxt::Error::Runtime(error) => Error::Dispatch(TxFailed::from(&error)),
+        e => Error::Rpc(format!("tx submitted, but can not fetch events: {e}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use subxt::ext::codec::Decode;
+
+    #[test]
+    fn test_tx_failed() {
+        let metadata: subxt::Metadata =
+            Decode::decode(&mut &include_bytes!("../ip_onchain_metadata.scale")[..]).unwrap();
+        let pallet = metadata.pallet_by_name("IPOnchain").unwrap();
+        let variant = pallet
+            .error_variant_by_index(0)
+            .expect("IPOnchain has errors");
+
+        // DispatchError::Module { index, error: [u8; 4] }
+        let bytes = [3, pallet.index(), 0, 0, 0, 0];
+        let error = DispatchError::decode_from(&bytes[..], metadata.clone()).unwrap();
+        let failed = TxFailed::from(&error);
+        assert_eq!(failed.code, format!("IPOnchain::{}", variant.name));
+        assert_eq!(failed.docs, variant.docs.join(" ").trim());
+
+        let failed = TxFailed::from(&DispatchError::BadOrigin);
+        assert_eq!(failed.code, "BadOrigin");
+        assert_eq!(failed.to_string(), "tx failed: BadOrigin: Bad origin.");
+
+        let error = events_error(subxt::Error::Runtime(DispatchError::BadOrigin));
+        assert!(matches!(error, Error::Dispatch(f) if f == failed));
+    }
+
+    #[test]
+    fn test_tx_outcome() {
+        let submitted = TxOutcome::Submitted(1).map(|n| n + 1);
+        assert_eq!(submitted.print_unsent().unwrap(), Some(2));
+
+        let built = TxOutcome::<u32>::Built("tx.json".into());
+        assert!(matches!(built.into_result(), Err(Error::Config(_))));
+
+        let dry_run = |result| {
+            TxOutcome::<u32>::DryRun(DryRun {
+                result,
+                partial_fee: 1,
+                ref_time: 2,
+                proof_size: 3,
+            })
+        };
+        assert_eq!(dry_run(Ok(())).print_unsent().unwrap(), None);
+        let failed = TxFailed {
+            code: "BadOrigin".into(),
+            docs: String::new(),
+        };
+        let error = dry_run(Err(failed.clone())).print_unsent().unwrap_err();
+        assert!(matches!(error, Error::Dispatch(f) if f == failed));
+    }
+}
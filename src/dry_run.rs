@@ -0,0 +1,160 @@
+use crate::dispatch::TxFailed;
+use crate::error::Error;
+use crate::signer::Tx;
+use std::fmt;
+use subxt::error::DispatchError;
+use subxt::ext::codec::{Compact, Decode, Encode, Input};
+use subxt::{OnlineClient, PolkadotConfig};
+
+/// Result of `--dry-run`, returned as `TxOutcome::DryRun` instead of the tx progress.
+#[derive(Debug)]
+pub struct DryRun {
+    /// Predicted dispatch result.
+    pub result: Result<(), TxFailed>,
+    pub partial_fee: u128,
+    pub ref_time: u64,
+    pub proof_size: u64,
+}
+
+impl fmt::Display for DryRun {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.result {
+            Ok(()) => writeln!(f, "Dry run: success")?,
+            Err(failed) => writeln!(f, "Dry run: {failed}")?,
+        }
+        writeln!(
+            f,
+            "Weight: ref_time {}, proof_size {}",
+            self.ref_time, self.proof_size
+        )?;
+        write!(f, "Estimated fee: {}", self.partial_fee)
+    }
+}
+
+/// `ApplyExtrinsicResult`, the dispatch error stays encoded until it is decoded with the metadata.
+#[derive(Debug, PartialEq)]
+enum ApplyResult {
+    Success,
+    DispatchError(Vec<u8>),
+    Invalid(TxFailed),
+}
+
+impl Decode for ApplyResult {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, subxt::ext::codec::Error> {
+        match (input.read_byte()?, input.read_byte()?) {
+            (0, 0) => Ok(ApplyResult::Success),
+            (0, 1) => {
+                let mut error = vec![0; input.remaining_len()?.unwrap_or_default()];
+                input.read(&mut error)?;
+                Ok(ApplyResult::DispatchError(error))
+            }
+            (1, kind) => {
+                let variant = input.read_byte()?;
+                let custom = input.read_byte().ok();
+                Ok(ApplyResult::Invalid(validity_error(kind, variant, custom)))
+            }
+            _ => Err("bad ApplyExtrinsicResult".into()),
+        }
+    }
+}
+
+/// `TransactionValidityError`, the transaction would not even be included in a block.
+fn validity_error(kind: u8, variant: u8, custom: Option<u8>) -> TxFailed {
+    const INVALID: [(&str, &str); 13] = [
+        ("Call", "the call of the transaction is not expected"),
+        ("Payment", "the account can not pay the fees"),
+        ("Future", "the nonce is in the future"),
+        ("Stale", "the nonce is already used"),
+        ("BadProof", "bad signature"),
+        ("AncientBirthBlock", "the mortality block is too old"),
+        ("ExhaustsResources", "the block would be too full"),
+        ("Custom", ""),
+        ("BadMandatory", ""),
+        ("MandatoryValidation", ""),
+        ("BadSigner", "the signer can not send this transaction"),
+        ("IndeterminateImplicit", ""),
+        ("UnknownOrigin", ""),
+    ];
+    const UNKNOWN: [(&str, &str); 3] = [
+        ("CannotLookup", ""),
+        ("NoUnsignedValidator", ""),
+        ("Custom", ""),
+    ];
+
+    let (kind, names): (&str, &[(&str, &str)]) = match kind {
+        0 => ("InvalidTransaction", &INVALID),
+        _ => ("UnknownTransaction", &UNKNOWN),
+    };
+    let (name, docs) = names
+        .get(variant as usize)
+        .copied()
+        .unwrap_or(("Unknown", ""));
+    let code = match (name, custom) {
+        ("Custom", Some(custom)) => format!("{kind}::Custom::{custom}"),
+        _ => format!("{kind}::{name}"),
+    };
+    TxFailed {
+        code,
+        docs: docs.to_string(),
+    }
+}
+
+/// Applies the signed tx on top of the latest block state like `system_dryRun`
+/// and asks the fee and weight from `TransactionPaymentApi`.
+pub async fn dry_run(api: &OnlineClient<PolkadotConfig>, tx: &Tx) -> Result<DryRun, Error> {
+    let runtime_api = api.runtime_api().at_latest().await?;
+
+    let apply_result: ApplyResult = runtime_api
+        .call_raw("BlockBuilder_apply_extrinsic", Some(tx.encoded()))
+        .await
+        .map_err(|e| Error::Rpc(format!("dry run: {e}")))?;
+    let result = match apply_result {
+        ApplyResult::Success => Ok(()),
+        ApplyResult::Invalid(e) => Err(e),
+        ApplyResult::DispatchError(bytes) => {
+            let error = DispatchError::decode_from(bytes, api.metadata())
+                .map_err(|e| Error::Decode(format!("dry run dispatch error: {e}")))?;
+            Err(TxFailed::from(&error))
+        }
+    };
+
+    let mut params = tx.encoded().to_vec();
+    (tx.encoded().len() as u32).encode_to(&mut params);
+    // RuntimeDispatchInfo { weight: { ref_time, proof_size }, class, partial_fee }
+    let (ref_time, proof_size, _class, partial_fee): (Compact<u64>, Compact<u64>, u8, u128) =
+        runtime_api
+            .call_raw("TransactionPaymentApi_query_info", Some(&params))
+            .await
+            .map_err(|e| Error::Rpc(format!("fee estimation: {e}")))?;
+
+    Ok(DryRun {
+        result,
+        partial_fee,
+        ref_time: ref_time.0,
+        proof_size: proof_size.0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_result() {
+        let decode = |bytes: &[u8]| ApplyResult::decode(&mut &bytes[..]).unwrap();
+
+        assert_eq!(decode(&[0, 0]), ApplyResult::Success);
+        assert_eq!(
+            decode(&[0, 1, 3, 51, 2, 0, 0, 0]),
+            ApplyResult::DispatchError(vec![3, 51, 2, 0, 0, 0])
+        );
+        let invalid = |bytes: &[u8]| match decode(bytes) {
+            ApplyResult::Invalid(failed) => failed.code,
+            result => panic!("not invalid: {result:?}"),
+        };
+        assert_eq!(invalid(&[1, 0, 1]), "InvalidTransaction::Payment");
+        assert_eq!(invalid(&[1, 0, 7, 4]), "InvalidTransaction::Custom::4");
+        assert_eq!(invalid(&[1, 1, 0]), "UnknownTransaction::CannotLookup");
+        assert!(ApplyResult::decode(&mut &[2u8, 0][..]).is_err());
+    }
+}
@@ -0,0 +1,172 @@
+use crate::dispatch::TxFailed;
+use crate::poll::Waiting;
+use std::fmt;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Error of the library functions, to match on the kind of failure.
+#[derive(Debug)]
+pub enum Error {
+    /// Bad arguments, config or input files.
+    Config(String),
+    /// Signer, keystore or key derivation.
+    Key(String),
+    /// Connection to the node and rpc calls.
+    Rpc(String),
+    /// Missing on chain, like an entity or an authority.
+    NotFound(String),
+    /// Fingerprint api answered with an error status.
+    Api {
+        status: u16,
+        body: String,
+    },
+    /// Fingerprint api request failed without an answer.
+    Http(String),
+    /// Transaction failed on chain.
+    Dispatch(TxFailed),
+    /// Waited too long for the chain or a worker.
+    Timeout(String),
+    /// Stopped by Ctrl-C while waiting, the job keeps running.
+    Cancelled(Waiting),
+    /// The job failed, waiting longer will not make it done.
+    JobFailed(Waiting),
+    /// Bad json, hex or scale data.
+    Decode(String),
+    /// `verify-entity` found the metadata unreachable or not matching.
+    Verification(String),
+    Io(std::io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Config(e)
+            | Error::Key(e)
+            | Error::Rpc(e)
+            | Error::NotFound(e)
+            | Error::Http(e)
+            | Error::Timeout(e)
+            | Error::Decode(e)
+            | Error::Verification(e) => write!(f, "{e}"),
+            Error::Api { status, body } => write!(f, "fingerprint api error {status}: {body}"),
+            Error::Dispatch(failed) => write!(f, "{failed}"),
+            Error::Io(e) => write!(f, "{e}"),
+            Error::Cancelled(waiting) => write!(f, "stopped waiting for {waiting}"),
+            Error::JobFailed(waiting) => write!(f, "{waiting} failed"),
+        }
+    }
+}
+
+impl Error {
+    /// Connection errors, rate limits and server errors, which may pass on retry.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Error::Http(_) | Error::Rpc(_) => true,
+            Error::Api { status, .. } => *status == 429 || *status >= 500,
+            _ => false,
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<subxt::Error> for Error {
+    fn from(e: subxt::Error) -> Self {
+        match e {
+            subxt::Error::Runtime(error) => Error::Dispatch(TxFailed::from(&error)),
+            e => Error::Rpc(e.to_string()),
+        }
+    }
+}
+
+impl From<subxt::ext::subxt_rpcs::Error> for Error {
+    fn from(e: subxt::ext::subxt_rpcs::Error) -> Self {
+        Error::Rpc(e.to_string())
+    }
+}
+
+impl From<subxt::ext::subxt_core::Error> for Error {
+    fn from(e: subxt::ext::subxt_core::Error) -> Self {
+        Error::Decode(e.to_string())
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Http(e.to_string())
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Decode(format!("json: {e}"))
+    }
+}
+
+impl From<hex::FromHexError> for Error {
+    fn from(e: hex::FromHexError) -> Self {
+        Error::Decode(format!("hex: {e}"))
+    }
+}
+
+impl From<subxt::ext::codec::Error> for Error {
+    fn from(e: subxt::ext::codec::Error) -> Self {
+        Error::Decode(format!("scale: {e}"))
+    }
+}
+
+impl From<subxt::ext::scale_decode::Error> for Error {
+    fn from(e: subxt::ext::scale_decode::Error) -> Self {
+        Error::Decode(format!("scale: {e}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use subxt::error::DispatchError;
+
+    #[test]
+    fn test_from() {
+        let e = Error::from(subxt::Error::Runtime(DispatchError::BadOrigin));
+        assert!(matches!(e, Error::Dispatch(ref failed) if failed.code == "BadOrigin"));
+
+        let e = Error::from(subxt::Error::Other("connection closed".into()));
+        assert!(matches!(e, Error::Rpc(_)));
+
+        let e = Error::from(serde_json::from_str::<u32>("x").unwrap_err());
+        assert!(matches!(e, Error::Decode(_)));
+
+        let e = Error::Api {
+            status: 401,
+            body: "bad token".into(),
+        };
+        assert_eq!(e.to_string(), "fingerprint api error 401: bad token");
+    }
+
+    #[test]
+    fn test_is_transient() {
+        assert!(Error::Http("connection reset".into()).is_transient());
+        let api = |status| Error::Api {
+            status,
+            body: String::new(),
+        };
+        assert!(api(503).is_transient());
+        assert!(api(429).is_transient());
+        assert!(!api(401).is_transient());
+        assert!(!Error::Config("no token".into()).is_transient());
+    }
+}
@@ -0,0 +1,258 @@
+use crate::block::{extrinsic_signers, Block};
+use crate::client::MubertClient;
+use crate::error::Error;
+use crate::ip_onchain_runtime;
+use crate::ip_onchain_runtime::ip_onchain::events::{
+    EntityAdded, EntityEdited, EntityMovedForeign, EntityWraped,
+};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use subxt::events::{EventDetails, Phase};
+use subxt::utils::{AccountId32, H256};
+use subxt::PolkadotConfig;
+
+/// Entity events found in already scanned blocks, stored per chain.
+#[derive(Serialize, Deserialize, Default)]
+struct HistoryCache {
+    /// Sorted, non overlapping, inclusive block ranges.
+    scanned: Vec<(u32, u32)>,
+    events: Vec<EntityEvent>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct EntityEvent {
+    block_number: u32,
+    block_hash: H256,
+    event_index: u32,
+    extrinsic_index: Option<u32>,
+    signer: Option<AccountId32>,
+    event: String,
+    entity_id: u32,
+}
+
+#[derive(Serialize)]
+struct TimelineEntry<T> {
+    #[serde(flatten)]
+    event: EntityEvent,
+    before: Option<T>,
+    after: Option<T>,
+}
+
+impl HistoryCache {
+    fn path(cache_dir: &Option<PathBuf>, genesis_hash: H256) -> Result<PathBuf, Error> {
+        let cache_dir = match cache_dir {
+            Some(cache_dir) => cache_dir.clone(),
+            None => dirs::cache_dir()
+                .ok_or_else(|| Error::Config("no cache dir, set --cache-dir".into()))?
+                .join("mubert-cli"),
+        };
+        Ok(cache_dir.join(format!("entity_history_{genesis_hash:?}.json")))
+    }
+
+    fn load(path: &PathBuf) -> Result<Self, Error> {
+        if !path.exists() {
+            return Ok(HistoryCache::default());
+        }
+        let data = std::fs::read_to_string(path)
+            .map_err(|e| Error::Config(format!("read history cache {:?}: {e}", path)))?;
+        serde_json::from_str(data.as_str()).map_err(|e| Error::Decode(format!("parsing json: {e}")))
+    }
+
+    fn save(&self, path: &PathBuf) -> Result<(), Error> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string(self)?)
+            .map_err(|e| Error::Config(format!("write history cache {:?}: {e}", path)))?;
+        Ok(())
+    }
+
+    fn add_scanned(&mut self, from: u32, to: u32) {
+        self.scanned.push((from, to));
+        self.scanned = merge_ranges(std::mem::take(&mut self.scanned));
+    }
+}
+
+fn merge_ranges(mut ranges: Vec<(u32, u32)>) -> Vec<(u32, u32)> {
+    ranges.sort();
+    let mut merged: Vec<(u32, u32)> = Vec::new();
+    for (from, to) in ranges {
+        match merged.last_mut() {
+            Some(last) if from <= last.1.saturating_add(1) => last.1 = last.1.max(to),
+            _ => merged.push((from, to)),
+        }
+    }
+    merged
+}
+
+/// Parts of `from..=to` not covered by the scanned ranges.
+fn missing_ranges(scanned: &[(u32, u32)], from: u32, to: u32) -> Vec<(u32, u32)> {
+    let mut missing = Vec::new();
+    let mut next = from;
+    for &(start, end) in scanned {
+        if end < next || start > to {
+            continue;
+        }
+        if start > next {
+            missing.push((next, start - 1));
+        }
+        if end >= to {
+            return missing;
+        }
+        next = end + 1;
+    }
+    if next <= to {
+        missing.push((next, to));
+    }
+    missing
+}
+
+fn entity_event_id(event: &EventDetails<PolkadotConfig>) -> Result<Option<u32>, Error> {
+    if let Some(e) = event.as_event::<EntityAdded>()? {
+        return Ok(Some(e.entity_id));
+    }
+    if let Some(e) = event.as_event::<EntityEdited>()? {
+        return Ok(Some(e.entity_id));
+    }
+    if let Some(e) = event.as_event::<EntityWraped>()? {
+        return Ok(Some(e.entity_id));
+    }
+    if let Some(e) = event.as_event::<EntityMovedForeign>()? {
+        return Ok(Some(e.entity_id));
+    }
+    Ok(None)
+}
+
+async fn scan_block(block: &Block) -> Result<Vec<EntityEvent>, Error> {
+    let mut found = Vec::new();
+
+    let events = block.events().await?;
+    for event in events.iter() {
+        let event = event?;
+        let Some(entity_id) = entity_event_id(&event)? else {
+            continue;
+        };
+
+        let extrinsic_index = match event.phase() {
+            Phase::ApplyExtrinsic(index) => Some(index),
+            _ => None,
+        };
+
+        found.push(EntityEvent {
+            block_number: block.number(),
+            block_hash: block.hash(),
+            event_index: event.index(),
+            extrinsic_index,
+            signer: None,
+            event: event.variant_name().to_string(),
+            entity_id,
+        });
+    }
+
+    if found.iter().any(|e| e.extrinsic_index.is_some()) {
+        let signers = extrinsic_signers(block).await?;
+        for event in found.iter_mut() {
+            event.signer = event
+                .extrinsic_index
+                .and_then(|index| signers.get(&index).cloned());
+        }
+    }
+
+    Ok(found)
+}
+
+pub async fn entity_history(
+    client: &MubertClient,
+    entity_id: u32,
+    from_block: u32,
+    to_block: Option<u32>,
+    cache_dir: &Option<PathBuf>,
+) -> Result<(), Error> {
+    let (api, rpc) = (client.api(), client.rpc());
+
+    // only finalized blocks are scanned, so the cache never holds forked blocks
+    let finalized = api.blocks().at_latest().await?;
+    let to_block = to_block
+        .unwrap_or(finalized.number())
+        .min(finalized.number());
+
+    let cache_path = HistoryCache::path(cache_dir, api.genesis_hash())?;
+    let mut cache = HistoryCache::load(&cache_path)?;
+
+    for (from, to) in missing_ranges(&cache.scanned, from_block, to_block) {
+        eprintln!("scanning blocks {from}..={to}");
+        for number in from..=to {
+            let hash = rpc
+                .chain_get_block_hash(Some(number.into()))
+                .await?
+                .ok_or(Error::NotFound(format!("block {number} not found")))?;
+            let block = api.blocks().at(hash).await?;
+
+            cache.events.extend(scan_block(&block).await?);
+
+            if number % 1000 == 0 {
+                cache.add_scanned(from, number);
+                cache.save(&cache_path)?;
+            }
+        }
+        cache.add_scanned(from, to);
+        cache.save(&cache_path)?;
+    }
+
+    let mut timeline: Vec<EntityEvent> = cache
+        .events
+        .iter()
+        .filter(|e| e.entity_id == entity_id)
+        .filter(|e| e.block_number >= from_block && e.block_number <= to_block)
+        .cloned()
+        .collect();
+    timeline.sort_by_key(|e| (e.block_number, e.event_index));
+    timeline.dedup_by_key(|e| (e.block_number, e.event_index));
+
+    let query = ip_onchain_runtime::storage()
+        .ip_onchain()
+        .entities(entity_id);
+
+    for event in timeline {
+        let block = api.blocks().at(event.block_hash).await?;
+        let before = match block.number() {
+            0 => None,
+            _ => {
+                api.storage()
+                    .at(block.header().parent_hash)
+                    .fetch(&query)
+                    .await?
+            }
+        };
+        let after = block.storage().fetch(&query).await?;
+
+        let entry = TimelineEntry {
+            event,
+            before,
+            after,
+        };
+        println!("{}", serde_json::to_string(&entry)?);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_ranges() {
+        let ranges = vec![(10, 20), (0, 5), (6, 8), (15, 30), (40, 50)];
+        assert_eq!(merge_ranges(ranges), [(0, 8), (10, 30), (40, 50)]);
+    }
+
+    #[test]
+    fn test_missing_ranges() {
+        let scanned = [(0, 8), (10, 30), (40, 50)];
+        assert_eq!(missing_ranges(&scanned, 5, 45), [(9, 9), (31, 39)]);
+        assert_eq!(missing_ranges(&scanned, 51, 60), [(51, 60)]);
+        assert_eq!(missing_ranges(&scanned, 12, 20), []);
+        assert_eq!(missing_ranges(&[], 0, 3), [(0, 3)]);
+    }
+}
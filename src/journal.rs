@@ -0,0 +1,177 @@
+use crate::error::Error;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+#[derive(clap::Args, Clone, Debug, Default)]
+pub struct ResumeArgs {
+    #[arg(
+        long,
+        conflicts_with = "resume_arweave_task",
+        help = "wait for this fingerprint job instead of uploading the audio"
+    )]
+    pub resume_fingerprint_job: Option<String>,
+    #[arg(
+        long,
+        help = "wait for this arweave task instead of uploading the audio and the metadata"
+    )]
+    pub resume_arweave_task: Option<u64>,
+    #[arg(
+        long,
+        env = "MUBERT_JOURNAL",
+        help = "journal of the done upload-ip stages, <cache dir>/mubert-cli/journal/upload-ip-<file and data hash>.json by default"
+    )]
+    pub journal: Option<PathBuf>,
+}
+
+/// Stages of `upload-ip` done so far, written after each one so a new run of the same
+/// file and data continues from the last one. Removed once the entity is created.
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+pub struct UploadJournal {
+    pub fingerprint_job: Option<String>,
+    pub fingerprint: Option<String>,
+    pub arweave_task: Option<u64>,
+    pub metadata_url: Option<String>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl UploadJournal {
+    /// Journal of the file and data, a changed file or data gets a new one.
+    pub fn default_path(file: &Path, data: &str) -> Result<PathBuf, Error> {
+        let metadata = std::fs::metadata(file)
+            .map_err(|e| Error::Config(format!("read file {:?}: {e}", file)))?;
+        let modified = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+
+        let mut hasher = Sha256::new();
+        hasher.update(file.canonicalize()?.to_string_lossy().as_bytes());
+        hasher.update(metadata.len().to_le_bytes());
+        hasher.update(modified.to_le_bytes());
+        hasher.update(data.as_bytes());
+        let key = hex::encode(&hasher.finalize()[..8]);
+
+        Ok(dirs::cache_dir()
+            .ok_or_else(|| Error::Config("no cache dir, set --journal".into()))?
+            .join("mubert-cli")
+            .join("journal")
+            .join(format!("upload-ip-{key}.json")))
+    }
+
+    /// The journal of the path, an empty one if there is none yet.
+    pub fn open(path: PathBuf) -> Result<Self, Error> {
+        let mut journal: UploadJournal = match path.exists() {
+            true => {
+                let data = std::fs::read_to_string(&path)
+                    .map_err(|e| Error::Config(format!("read journal {:?}: {e}", path)))?;
+                serde_json::from_str(data.as_str())
+                    .map_err(|e| Error::Decode(format!("parsing journal {:?}: {e}", path)))?
+            }
+            false => UploadJournal::default(),
+        };
+        journal.path = path;
+        Ok(journal)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn is_started(&self) -> bool {
+        self.fingerprint_job.is_some() || self.arweave_task.is_some()
+    }
+
+    /// Given ids replace the journal ones, with the stages after them.
+    pub fn resume(&mut self, args: &ResumeArgs) {
+        if let Some(job) = &args.resume_fingerprint_job {
+            *self = UploadJournal {
+                fingerprint_job: Some(job.clone()),
+                path: std::mem::take(&mut self.path),
+                ..Default::default()
+            };
+        }
+        if let Some(task) = args.resume_arweave_task {
+            self.arweave_task = Some(task);
+            self.metadata_url = None;
+        }
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string_pretty(self)?)
+            .map_err(|e| Error::Config(format!("write journal {:?}: {e}", self.path)))?;
+        Ok(())
+    }
+
+    pub fn remove(self) -> Result<(), Error> {
+        match std::fs::remove_file(&self.path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_journal() {
+        let dir = std::env::temp_dir().join(format!("mubert-journal-{}", std::process::id()));
+        let path = dir.join("upload-ip.json");
+
+        let mut journal = UploadJournal::open(path.clone()).unwrap();
+        assert!(!journal.is_started());
+        journal.fingerprint_job = Some("job-1".into());
+        journal.fingerprint = Some("fp".into());
+        journal.save().unwrap();
+
+        let mut journal = UploadJournal::open(path.clone()).unwrap();
+        assert!(journal.is_started());
+        assert_eq!(journal.fingerprint.as_deref(), Some("fp"));
+
+        journal.resume(&ResumeArgs {
+            resume_fingerprint_job: Some("job-2".into()),
+            ..Default::default()
+        });
+        assert_eq!(
+            journal,
+            UploadJournal {
+                fingerprint_job: Some("job-2".into()),
+                path: path.clone(),
+                ..Default::default()
+            }
+        );
+
+        journal.metadata_url = Some("url".into());
+        journal.resume(&ResumeArgs {
+            resume_arweave_task: Some(7),
+            ..Default::default()
+        });
+        assert_eq!(journal.arweave_task, Some(7));
+        assert_eq!(journal.metadata_url, None);
+
+        journal.remove().unwrap();
+        assert!(!path.exists());
+        UploadJournal::open(path).unwrap().remove().unwrap();
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_default_path() {
+        let file = std::env::temp_dir().join(format!("mubert-journal-{}.wav", std::process::id()));
+        std::fs::write(&file, b"audio").unwrap();
+
+        let path = UploadJournal::default_path(&file, "{}").unwrap();
+        assert_eq!(path, UploadJournal::default_path(&file, "{}").unwrap());
+        assert_ne!(path, UploadJournal::default_path(&file, "{ }").unwrap());
+
+        std::fs::remove_file(file).unwrap();
+    }
+}
@@ -0,0 +1,513 @@
+use crate::error::Error;
+use crate::signer::{ed25519_from_uri, uri_seed, Scheme, Signer};
+use base64::Engine;
+use crypto_secretbox::aead::{Aead, KeyInit};
+use crypto_secretbox::{Key, Nonce, XSalsa20Poly1305};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+use subxt::utils::AccountId32;
+use subxt_signer::bip39::Mnemonic;
+use subxt_signer::{DeriveJunction, SecretUri};
+
+// polkadot.js keyring json v3: scrypt + xsalsa20-poly1305 over a pkcs8 encoded keypair
+const PKCS8_HEADER: [u8; 16] = [48, 83, 2, 1, 1, 48, 5, 6, 3, 43, 101, 112, 4, 34, 4, 32];
+const PKCS8_DIVIDER: [u8; 5] = [161, 35, 3, 33, 0];
+const SCRYPT_LOG_N: u8 = 15;
+const SCRYPT_P: u32 = 1;
+const SCRYPT_R: u32 = 8;
+
+#[derive(clap::Args, Clone, Debug, Default)]
+pub struct KeystoreArgs {
+    #[arg(
+        long,
+        env = "MUBERT_KEYSTORE_DIR",
+        help = "keystore dir, <user config dir>/mubert-cli/keystore by default"
+    )]
+    pub keystore_dir: Option<PathBuf>,
+    #[arg(
+        long,
+        env = "MUBERT_KEY_PASSWORD",
+        hide_env_values = true,
+        help = "keystore password, asked on the terminal when not set"
+    )]
+    pub key_password: Option<String>,
+}
+
+/// Key file in the polkadot.js json export format.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct KeyJson {
+    encoded: String,
+    encoding: Encoding,
+    address: AccountId32,
+    #[serde(default)]
+    meta: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct Encoding {
+    content: Vec<String>,
+    #[serde(rename = "type")]
+    kind: Vec<String>,
+    version: String,
+}
+
+#[derive(Serialize)]
+pub struct KeyInfo {
+    pub name: String,
+    pub address: AccountId32,
+    pub public_key: String,
+    pub scheme: Scheme,
+}
+
+pub struct Keystore {
+    dir: PathBuf,
+}
+
+impl KeystoreArgs {
+    pub fn keystore(&self) -> Result<Keystore, Error> {
+        let dir = match &self.keystore_dir {
+            Some(dir) => dir.clone(),
+            None => dirs::config_dir()
+                .ok_or_else(|| Error::Key("no config dir, set --keystore-dir".into()))?
+                .join("mubert-cli")
+                .join("keystore"),
+        };
+        Ok(Keystore { dir })
+    }
+
+    pub fn password(&self, confirm: bool) -> Result<String, Error> {
+        if let Some(password) = &self.key_password {
+            return Ok(password.clone());
+        }
+        let password = rpassword::prompt_password("Keystore password: ")?;
+        if confirm && rpassword::prompt_password("Repeat password: ")? != password {
+            return Err(Error::Key("passwords do not match".into()));
+        }
+        Ok(password)
+    }
+}
+
+impl Keystore {
+    fn path(&self, name: &str) -> Result<PathBuf, Error> {
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+            && !name.starts_with('.');
+        if !valid {
+            return Err(Error::Key(format!(
+                "bad key name {name:?}: use letters, digits, '-', '_' or '.'"
+            )));
+        }
+        Ok(self.dir.join(format!("{name}.json")))
+    }
+
+    pub fn get(&self, name: &str) -> Result<KeyJson, Error> {
+        let path = self.path(name)?;
+        let data = std::fs::read_to_string(&path)
+            .map_err(|e| Error::Key(format!("read key {name}: {e}")))?;
+        serde_json::from_str(data.as_str()).map_err(|e| Error::Decode(format!("parsing json: {e}")))
+    }
+
+    pub fn add(&self, name: &str, mut key: KeyJson) -> Result<KeyInfo, Error> {
+        let path = self.path(name)?;
+        key.meta.insert("name".to_string(), name.into());
+
+        // the key is only ever readable by the owner, there is no window with the umask mode
+        let mut dir = std::fs::DirBuilder::new();
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+            dir.mode(0o700);
+            options.mode(0o600);
+        }
+        dir.recursive(true)
+            .create(&self.dir)
+            .map_err(|e| Error::Key(format!("create keystore dir {:?}: {e}", self.dir)))?;
+        let mut file = options.open(&path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::AlreadyExists => Error::Key(format!("key {name} already exists")),
+            _ => Error::Key(format!("write key {:?}: {e}", path)),
+        })?;
+        file.write_all(serde_json::to_string_pretty(&key)?.as_bytes())
+            .map_err(|e| Error::Key(format!("write key {:?}: {e}", path)))?;
+        key.info(name)
+    }
+
+    pub fn remove(&self, name: &str) -> Result<(), Error> {
+        let path = self.path(name)?;
+        std::fs::remove_file(&path).map_err(|e| Error::Key(format!("remove key {name}: {e}")))?;
+        Ok(())
+    }
+
+    pub fn list(&self) -> Result<Vec<KeyInfo>, Error> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut keys = Vec::new();
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            keys.push(self.get(name)?.info(name)?);
+        }
+        keys.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(keys)
+    }
+}
+
+impl KeyJson {
+    /// Encrypts the key of the secret uri, only sr25519 and ed25519 keys can be stored.
+    pub fn encrypt(uri: &SecretUri, scheme: Scheme, password: &str) -> Result<Self, Error> {
+        let (secret_key, public_key) = match scheme {
+            Scheme::Sr25519 => {
+                let secret = sr25519_secret(uri)?;
+                (
+                    secret.to_ed25519_bytes().to_vec(),
+                    secret.to_public().to_bytes(),
+                )
+            }
+            Scheme::Ed25519 => {
+                let key = ed25519_from_uri(uri)?;
+                let public_key: [u8; 32] = ed25519_zebra::VerificationKey::from(&key).into();
+                let seed: [u8; 32] = key.into();
+                // polkadot.js keeps the ed25519 secret as seed + public key
+                ([seed, public_key].concat(), public_key)
+            }
+            Scheme::Ecdsa => {
+                return Err(Error::Key(
+                    "ecdsa keys are not supported by the keystore".into(),
+                ))
+            }
+        };
+
+        let mut plaintext = Vec::new();
+        plaintext.extend_from_slice(&PKCS8_HEADER);
+        plaintext.extend_from_slice(&secret_key);
+        plaintext.extend_from_slice(&PKCS8_DIVIDER);
+        plaintext.extend_from_slice(&public_key);
+
+        let mut salt = [0u8; 32];
+        let mut nonce = [0u8; 24];
+        rand::thread_rng().fill_bytes(&mut salt);
+        rand::thread_rng().fill_bytes(&mut nonce);
+
+        let cipher = XSalsa20Poly1305::new(&password_key(password, &salt)?);
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce), plaintext.as_slice())
+            .map_err(|e| Error::Key(format!("encrypt key: {e}")))?;
+
+        let mut encoded = Vec::new();
+        encoded.extend_from_slice(&salt);
+        encoded.extend_from_slice(&(1u32 << SCRYPT_LOG_N).to_le_bytes());
+        encoded.extend_from_slice(&SCRYPT_P.to_le_bytes());
+        encoded.extend_from_slice(&SCRYPT_R.to_le_bytes());
+        encoded.extend_from_slice(&nonce);
+        encoded.extend_from_slice(&ciphertext);
+
+        let when_created = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        let mut meta = serde_json::Map::new();
+        meta.insert("whenCreated".to_string(), when_created.into());
+
+        Ok(KeyJson {
+            encoded: base64::engine::general_purpose::STANDARD.encode(encoded),
+            encoding: Encoding {
+                content: vec!["pkcs8".to_string(), scheme_name(scheme).to_string()],
+                kind: vec!["scrypt".to_string(), "xsalsa20-poly1305".to_string()],
+                version: "3".to_string(),
+            },
+            address: AccountId32(public_key),
+            meta,
+        })
+    }
+
+    pub fn scheme(&self) -> Result<Scheme, Error> {
+        let content = &self.encoding.content;
+        if content.iter().any(|c| c == "sr25519") {
+            Ok(Scheme::Sr25519)
+        } else if content.iter().any(|c| c == "ed25519") {
+            Ok(Scheme::Ed25519)
+        } else {
+            Err(Error::Key(format!("unsupported key content {content:?}")))
+        }
+    }
+
+    pub fn info(&self, name: &str) -> Result<KeyInfo, Error> {
+        Ok(KeyInfo {
+            name: name.to_string(),
+            address: self.address.clone(),
+            public_key: format!("0x{}", hex::encode(self.address.0)),
+            scheme: self.scheme()?,
+        })
+    }
+
+    pub fn decrypt(&self, password: &str) -> Result<Signer, Error> {
+        match self.scheme()? {
+            Scheme::Sr25519 => {
+                let json = serde_json::to_string(self)?;
+                let keypair = subxt_signer::polkadot_js_compat::decrypt_json(&json, password)
+                    .map_err(|e| Error::Key(format!("decrypt key: {e}")))?;
+                Ok(Signer::Sr25519(keypair))
+            }
+            _ => {
+                let secret_key = self.decrypt_secret(password)?;
+                let seed: [u8; 32] = secret_key[..32]
+                    .try_into()
+                    .map_err(|_| Error::Key("decrypt key: bad secret key".into()))?;
+                let key = ed25519_zebra::SigningKey::from(seed);
+                let signer = Signer::Ed25519(key);
+                if signer.account_id() != self.address {
+                    return Err(Error::Key(
+                        "decrypt key: the key does not match the address".into(),
+                    ));
+                }
+                Ok(signer)
+            }
+        }
+    }
+
+    fn decrypt_secret(&self, password: &str) -> Result<Vec<u8>, Error> {
+        if self.encoding.version != "3"
+            || !self.encoding.kind.iter().any(|t| t == "scrypt")
+            || !self.encoding.kind.iter().any(|t| t == "xsalsa20-poly1305")
+        {
+            return Err(Error::Key("decrypt key: unsupported encoding".into()));
+        }
+        let encoded = base64::engine::general_purpose::STANDARD
+            .decode(&self.encoded)
+            .map_err(|e| Error::Key(format!("decrypt key: {e}")))?;
+        if encoded.len() < 68 {
+            return Err(Error::Key("decrypt key: encoded data too short".into()));
+        }
+
+        let param = |i: usize| {
+            u32::from_le_bytes([encoded[i], encoded[i + 1], encoded[i + 2], encoded[i + 3]])
+        };
+        let (n, p, r) = (param(32), param(36), param(40));
+        // other params are refused, they come from the file and could eat up the cpu
+        if n != 1 << SCRYPT_LOG_N || p != SCRYPT_P || r != SCRYPT_R {
+            return Err(Error::Key(format!(
+                "decrypt key: unsupported scrypt params n={n} p={p} r={r}"
+            )));
+        }
+
+        let cipher = XSalsa20Poly1305::new(&password_key(password, &encoded[0..32])?);
+        let plaintext = cipher
+            .decrypt(Nonce::from_slice(&encoded[44..68]), &encoded[68..])
+            .map_err(|_| Error::Key("decrypt key: wrong password".into()))?;
+
+        if plaintext.len() != 117
+            || plaintext[0..16] != PKCS8_HEADER
+            || plaintext[80..85] != PKCS8_DIVIDER
+        {
+            return Err(Error::Key("decrypt key: invalid keys".into()));
+        }
+        Ok(plaintext[16..80].to_vec())
+    }
+}
+
+fn scheme_name(scheme: Scheme) -> &'static str {
+    match scheme {
+        Scheme::Sr25519 => "sr25519",
+        Scheme::Ed25519 => "ed25519",
+        Scheme::Ecdsa => "ecdsa",
+    }
+}
+
+fn password_key(password: &str, salt: &[u8]) -> Result<Key, Error> {
+    let params = scrypt::Params::new(SCRYPT_LOG_N, SCRYPT_R, SCRYPT_P, 32)
+        .map_err(|e| Error::Key(format!("scrypt params: {e}")))?;
+    let mut key = Key::default();
+    scrypt::scrypt(password.as_bytes(), salt, &params, &mut key)
+        .map_err(|e| Error::Key(format!("scrypt: {e}")))?;
+    Ok(key)
+}
+
+/// Same derivation as `subxt_signer::sr25519::Keypair::from_uri`, which keeps the secret private.
+fn sr25519_secret(uri: &SecretUri) -> Result<schnorrkel::SecretKey, Error> {
+    use schnorrkel::derive::{ChainCode, Derivation};
+    use schnorrkel::{ExpansionMode, MiniSecretKey};
+
+    let mini_secret = MiniSecretKey::from_bytes(&uri_seed(uri)?)
+        .map_err(|e| Error::Key(format!("sr25519 key: {e}")))?;
+    let secret = uri.junctions.iter().fold(
+        mini_secret.expand(ExpansionMode::Ed25519),
+        |secret, junction| match junction {
+            DeriveJunction::Soft(cc) => secret.derived_key_simple(ChainCode(*cc), []).0,
+            DeriveJunction::Hard(cc) => secret
+                .hard_derive_mini_secret_key(Some(ChainCode(*cc)), b"")
+                .0
+                .expand(ExpansionMode::Ed25519),
+        },
+    );
+    Ok(secret)
+}
+
+pub fn generate_phrase(words: usize) -> Result<String, Error> {
+    if !(12..=24).contains(&words) || !words.is_multiple_of(3) {
+        return Err(Error::Key(format!(
+            "bad mnemonic words count {words}: use 12, 15, 18, 21 or 24"
+        )));
+    }
+    let mut entropy = vec![0u8; words / 3 * 4];
+    rand::thread_rng().fill_bytes(&mut entropy);
+    let mnemonic = Mnemonic::from_entropy(&entropy)
+        .map_err(|e| Error::Key(format!("generate mnemonic: {e}")))?;
+    Ok(mnemonic.to_string())
+}
+
+pub fn key_generate(
+    keystore: &KeystoreArgs,
+    name: &str,
+    scheme: Scheme,
+    words: usize,
+) -> Result<(), Error> {
+    let phrase = generate_phrase(words)?;
+    let uri =
+        SecretUri::from_str(&phrase).map_err(|e| Error::Key(format!("bad secret uri: {e}")))?;
+    let key = KeyJson::encrypt(&uri, scheme, &keystore.password(true)?)?;
+    let info = keystore.keystore()?.add(name, key)?;
+
+    eprintln!("Secret phrase, write it down, it is not stored unencrypted:\n  {phrase}");
+    println!("{}", serde_json::to_string(&info)?);
+    Ok(())
+}
+
+pub fn key_import(
+    keystore: &KeystoreArgs,
+    name: &str,
+    suri: &Option<String>,
+    json_file: &Option<PathBuf>,
+    scheme: Scheme,
+) -> Result<(), Error> {
+    let key = match (suri, json_file) {
+        (Some(suri), None) => {
+            let uri = SecretUri::from_str(suri)
+                .map_err(|e| Error::Key(format!("bad secret uri: {e}")))?;
+            KeyJson::encrypt(&uri, scheme, &keystore.password(true)?)?
+        }
+        (None, Some(json_file)) => {
+            let data = std::fs::read_to_string(json_file)
+                .map_err(|e| Error::Key(format!("read json_file {:?}: {e}", json_file)))?;
+            let key: KeyJson = serde_json::from_str(data.as_str())
+                .map_err(|e| Error::Decode(format!("parsing json: {e}")))?;
+            // the file keeps its own password, make sure it is known before storing it
+            key.decrypt(&keystore.password(false)?)?;
+            key
+        }
+        _ => return Err(Error::Key("give either --suri or --json-file".into())),
+    };
+
+    let info = keystore.keystore()?.add(name, key)?;
+    println!("{}", serde_json::to_string(&info)?);
+    Ok(())
+}
+
+pub fn key_list(keystore: &KeystoreArgs) -> Result<(), Error> {
+    for info in keystore.keystore()?.list()? {
+        println!("{}", serde_json::to_string(&info)?);
+    }
+    Ok(())
+}
+
+pub fn key_export_public(keystore: &KeystoreArgs, name: &str) -> Result<(), Error> {
+    let info = keystore.keystore()?.get(name)?.info(name)?;
+    println!("{}", serde_json::to_string(&info)?);
+    Ok(())
+}
+
+pub fn key_remove(keystore: &KeystoreArgs, name: &str) -> Result<(), Error> {
+    keystore.keystore()?.remove(name)?;
+    println!("key {name} removed");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use subxt_signer::sr25519;
+
+    #[test]
+    fn test_polkadot_js_compat() {
+        let uri = SecretUri::from_str("//Alice").unwrap();
+        let key = KeyJson::encrypt(&uri, Scheme::Sr25519, "whoisalice").unwrap();
+        let alice = sr25519::dev::alice().public_key().to_account_id();
+        assert_eq!(key.address, alice);
+
+        // decrypted by the subxt polkadot.js loader
+        let json = serde_json::to_string(&key).unwrap();
+        let keypair = subxt_signer::polkadot_js_compat::decrypt_json(&json, "whoisalice").unwrap();
+        assert_eq!(keypair.public_key().to_account_id(), alice);
+
+        assert_eq!(key.decrypt_secret("whoisalice").unwrap().len(), 64);
+        assert!(key.decrypt("wrong").is_err());
+    }
+
+    #[test]
+    fn test_ed25519() {
+        let uri = SecretUri::from_str("//Alice").unwrap();
+        let key = KeyJson::encrypt(&uri, Scheme::Ed25519, "pass").unwrap();
+        let signer = key.decrypt("pass").unwrap();
+        assert_eq!(
+            hex::encode(signer.account_id().0),
+            "88dc3417d5058ec4b4503e0c12ea1a0a89be200fe98922423d4334014fa6b0ee"
+        );
+        assert!(KeyJson::encrypt(&uri, Scheme::Ecdsa, "pass").is_err());
+    }
+
+    #[test]
+    fn test_keystore() {
+        let dir =
+            std::env::temp_dir().join(format!("mubert_cli_test_keystore_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let keystore = Keystore { dir };
+
+        let phrase = generate_phrase(12).unwrap();
+        assert_eq!(phrase.split(' ').count(), 12);
+        assert!(generate_phrase(13).is_err());
+        let uri = SecretUri::from_str(&phrase).unwrap();
+        let key = KeyJson::encrypt(&uri, Scheme::Sr25519, "pass").unwrap();
+
+        keystore.add("main", key.clone()).unwrap();
+        assert!(keystore.add("main", key).is_err());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = |path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+            assert_eq!(mode(&keystore.dir), 0o700);
+            assert_eq!(mode(&keystore.path("main").unwrap()), 0o600);
+        }
+        assert!(keystore.path("../main").is_err());
+
+        let keys = keystore.list().unwrap();
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].name, "main");
+        assert_eq!(keystore.get("main").unwrap().meta["name"], "main");
+
+        let args = crate::signer::SignerArgs {
+            key: Some("main".to_string()),
+            keystore: KeystoreArgs {
+                keystore_dir: Some(keystore.dir.clone()),
+                key_password: Some("pass".to_string()),
+            },
+            ..Default::default()
+        };
+        assert_eq!(args.signer().unwrap().account_id(), keys[0].address);
+
+        keystore.remove("main").unwrap();
+        assert!(keystore.list().unwrap().is_empty());
+        std::fs::remove_dir_all(&keystore.dir).unwrap();
+    }
+}
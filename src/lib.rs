@@ -1,7 +1,31 @@
+pub mod access;
 pub mod api;
+pub mod arweave;
+pub mod author;
+pub mod batch;
+pub mod block;
+pub mod client;
+pub mod config;
 pub mod create_authority;
+pub mod dispatch;
+pub mod dry_run;
+pub mod error;
 pub mod foreign_request;
+pub mod history;
+pub mod journal;
+pub mod keystore;
+pub mod list;
+pub mod mock_arweave;
+pub mod mock_fingerprint;
+pub mod multisig;
+pub mod poll;
+pub mod signer;
+pub mod tx;
 pub mod update_ip;
+pub mod upload;
+pub mod verify;
+pub mod watch;
+pub mod worker;
 
 #[subxt::subxt(
     runtime_metadata_path = "ip_onchain_metadata.scale",
@@ -10,6 +34,10 @@ pub mod update_ip;
         path = "pallet_ip_onchain::types::AuthorityDetails",
         derive = "serde::Deserialize, serde::Serialize"
     ),
+    derive_for_type(
+        path = "pallet_ip_onchain::types::AuthorDetails",
+        derive = "serde::Deserialize, serde::Serialize"
+    ),
     derive_for_type(
         path = "pallet_ip_onchain::types::AuthorityKind",
         derive = "serde::Deserialize, serde::Serialize"
@@ -74,16 +102,35 @@ pub mod update_ip;
         path = "sp_core::crypto::AccountId32",
         derive = "serde::Deserialize, serde::Serialize"
     ),
-    derive_for_type(path = "pallet_arweave::types::TaskState", derive = "PartialEq"),
+    derive_for_type(
+        path = "pallet_nfts::types::MintType",
+        derive = "serde::Deserialize, serde::Serialize"
+    ),
+    derive_for_type(
+        path = "pallet_arweave::types::TaskState",
+        derive = "clap::ValueEnum, PartialEq, serde::Serialize"
+    ),
     derive_for_type(
         path = "pallet_ip_onchain::types::AuthorityKind",
-        derive = "clap::ValueEnum"
+        derive = "clap::ValueEnum, PartialEq"
+    ),
+    derive_for_type(
+        path = "pallet_ip_onchain::types::IPEntityKind",
+        derive = "clap::ValueEnum, PartialEq"
     )
 )]
 pub mod ip_onchain_runtime {}
 
 use crate::ip_onchain_runtime::ip_onchain::calls::types::create_entity::Url;
-use crate::ip_onchain_runtime::runtime_types::pallet_ip_onchain::types::MetadataFeature;
+use crate::ip_onchain_runtime::runtime_types::pallet_ip_onchain::types::{
+    AuthorityAccessSetting, MetadataFeature,
+};
+use crate::ip_onchain_runtime::runtime_types::pallet_nfts::types::{
+    CollectionSetting, ItemSetting,
+};
+pub use client::{MubertClient, MubertClientBuilder};
+pub use dispatch::TxOutcome;
+pub use error::{Error, Result};
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 
@@ -111,6 +158,30 @@ where
     result
 }
 
+/// Like `calculate_flags`, but an unknown name is an error instead of being skipped.
+pub fn parse_flags<T>(flags: &[String]) -> Result<u64, Error>
+where
+    T: FromStr,
+    T: Bitmask,
+{
+    let mut result = 0u64;
+    for flag_str in flags {
+        let flag = T::from_str(flag_str).map_err(|_| {
+            let kind = std::any::type_name::<T>()
+                .rsplit("::")
+                .next()
+                .unwrap_or("flag");
+            Error::Config(format!("unknown {kind} {flag_str:?}"))
+        })?;
+        result |= flag.bitmask();
+    }
+    Ok(result)
+}
+
+pub fn has_flag<T: Bitmask>(flags: u64, flag: T) -> bool {
+    flags & flag.bitmask() != 0
+}
+
 impl From<String> for Url {
     fn from(item: String) -> Self {
         Url { 0: item.into() }
@@ -139,6 +210,75 @@ impl FromStr for MetadataFeature {
     }
 }
 
+impl Bitmask for AuthorityAccessSetting {
+    fn bitmask(&self) -> u64 {
+        match self {
+            AuthorityAccessSetting::EditAccess => 0x00000001,
+            AuthorityAccessSetting::CreateEntity => 0x00000002,
+            AuthorityAccessSetting::EditEntity => 0x00000004,
+            AuthorityAccessSetting::EditAuthority => 0x00000008,
+            AuthorityAccessSetting::CreateAuthorityCollection => 0x00000010,
+            AuthorityAccessSetting::WrapEntity => 0x00000020,
+        }
+    }
+}
+
+impl Bitmask for CollectionSetting {
+    fn bitmask(&self) -> u64 {
+        match self {
+            CollectionSetting::TransferableItems => 0x00000001,
+            CollectionSetting::UnlockedMetadata => 0x00000002,
+            CollectionSetting::UnlockedAttributes => 0x00000004,
+            CollectionSetting::UnlockedMaxSupply => 0x00000008,
+            CollectionSetting::DepositRequired => 0x00000010,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseCollectionSettingError;
+
+impl FromStr for CollectionSetting {
+    type Err = ParseCollectionSettingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "TransferableItems" => Ok(CollectionSetting::TransferableItems),
+            "UnlockedMetadata" => Ok(CollectionSetting::UnlockedMetadata),
+            "UnlockedAttributes" => Ok(CollectionSetting::UnlockedAttributes),
+            "UnlockedMaxSupply" => Ok(CollectionSetting::UnlockedMaxSupply),
+            "DepositRequired" => Ok(CollectionSetting::DepositRequired),
+            _ => Err(ParseCollectionSettingError),
+        }
+    }
+}
+
+impl Bitmask for ItemSetting {
+    fn bitmask(&self) -> u64 {
+        match self {
+            ItemSetting::Transferable => 0x00000001,
+            ItemSetting::UnlockedMetadata => 0x00000002,
+            ItemSetting::UnlockedAttributes => 0x00000004,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseItemSettingError;
+
+impl FromStr for ItemSetting {
+    type Err = ParseItemSettingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Transferable" => Ok(ItemSetting::Transferable),
+            "UnlockedMetadata" => Ok(ItemSetting::UnlockedMetadata),
+            "UnlockedAttributes" => Ok(ItemSetting::UnlockedAttributes),
+            _ => Err(ParseItemSettingError),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -164,6 +304,41 @@ mod tests {
         assert_eq!(result, 0);
     }
 
+    #[test]
+    fn test_has_flag() {
+        assert!(has_flag(0x00000001, MetadataFeature::Immutable));
+        assert!(!has_flag(0, MetadataFeature::Immutable));
+    }
+
+    #[test]
+    fn test_collection_settings() {
+        let flags = vec![
+            "TransferableItems".to_string(),
+            "UnlockedMetadata".to_string(),
+            "DepositRequired".to_string(),
+        ];
+        let result = calculate_flags::<CollectionSetting>(flags);
+        assert_eq!(result, 0x00000013);
+    }
+
+    #[test]
+    fn test_item_settings() {
+        let flags = vec!["Transferable".to_string(), "UnlockedAttributes".to_string()];
+        let result = calculate_flags::<ItemSetting>(flags);
+        assert_eq!(result, 0x00000005);
+    }
+
+    #[test]
+    fn test_unknown_setting() {
+        let flags = vec!["Transferable".to_string(), "Transferrable".to_string()];
+        let result = parse_flags::<ItemSetting>(&flags);
+        assert!(matches!(
+            result,
+            Err(Error::Config(ref e)) if e == "unknown ItemSetting \"Transferrable\""
+        ));
+        assert_eq!(parse_flags::<ItemSetting>(&flags[..1]).unwrap(), 0x00000001);
+    }
+
     #[test]
     fn test_duplicate_flags() {
         let flags = vec!["Immutable".to_string(), "Immutable".to_string()];
@@ -0,0 +1,216 @@
+use crate::block::{At, AtBlock, Block};
+use crate::client::MubertClient;
+use crate::error::Error;
+use crate::ip_onchain_runtime;
+use crate::ip_onchain_runtime::runtime_types::pallet_ip_onchain::types::{
+    AuthorityKind, IPEntityKind,
+};
+use serde::Serialize;
+use subxt::storage::Address;
+use subxt::utils::Yes;
+
+#[derive(clap::Args, Clone, Default)]
+pub struct Pagination {
+    #[arg(long, help = "max number of results")]
+    pub limit: Option<usize>,
+    #[arg(long, help = "return only ids greater than this one")]
+    pub start_after: Option<u64>,
+    #[arg(
+        long,
+        help = "print by id once all are read, instead of in storage order while they are read"
+    )]
+    pub sorted: bool,
+}
+
+#[derive(Serialize)]
+struct Item<T> {
+    id: u32,
+    #[serde(flatten)]
+    details: T,
+}
+
+/// Storage maps of the pallet are `Blake2_128Concat` hashed, so the raw u32 id
+/// is the last 4 bytes of the storage key.
+fn key_id(key_bytes: &[u8]) -> Result<u32, Error> {
+    let id: [u8; 4] = key_bytes
+        .get(key_bytes.len().saturating_sub(4)..)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| Error::Decode("bad storage key".into()))?;
+
+    Ok(u32::from_le_bytes(id))
+}
+
+/// Storage iteration order is the hash order, ids are sorted to make pages stable.
+fn paginate<T>(mut items: Vec<Item<T>>, pagination: &Pagination) -> Vec<Item<T>> {
+    items.sort_by_key(|item| item.id);
+    items
+        .into_iter()
+        .filter(|item| {
+            pagination
+                .start_after
+                .is_none_or(|start| u64::from(item.id) > start)
+        })
+        .take(pagination.limit.unwrap_or(usize::MAX))
+        .collect()
+}
+
+fn print_line<T: Serialize>(block: &Block, item: Item<T>) -> Result<(), Error> {
+    println!("{}", serde_json::to_string(&AtBlock::new(block, item))?);
+    Ok(())
+}
+
+/// Prints the kept values of the storage map as json lines while they are read, in the
+/// order of their hashed keys. With `--sorted` they are printed by id once all are read.
+async fn print_items<Addr>(
+    block: &Block,
+    query: Addr,
+    pagination: &Pagination,
+    keep: impl Fn(&Addr::Target) -> bool,
+) -> Result<(), Error>
+where
+    Addr: Address<IsIterable = Yes> + 'static,
+    Addr::Keys: 'static + Sized,
+    Addr::Target: Serialize,
+{
+    let mut results = block.storage().iter(query).await?;
+
+    let mut sorted = Vec::new();
+    let mut printed = 0;
+    while let Some(kv) = results.next().await {
+        let kv = kv?;
+        let id = key_id(&kv.key_bytes)?;
+        if pagination
+            .start_after
+            .is_some_and(|start| u64::from(id) <= start)
+            || !keep(&kv.value)
+        {
+            continue;
+        }
+
+        let item = Item {
+            id,
+            details: kv.value,
+        };
+        if pagination.sorted {
+            sorted.push(item);
+            continue;
+        }
+        if pagination.limit.is_some_and(|limit| printed >= limit) {
+            return Ok(());
+        }
+        print_line(block, item)?;
+        printed += 1;
+    }
+
+    for item in paginate(sorted, pagination) {
+        print_line(block, item)?;
+    }
+    Ok(())
+}
+
+pub async fn list_entities(
+    client: &MubertClient,
+    authority_id: Option<u32>,
+    kind: Option<IPEntityKind>,
+    pagination: &Pagination,
+    at: &Option<At>,
+) -> Result<(), Error> {
+    let block = client.block(at).await?;
+    let query = ip_onchain_runtime::storage().ip_onchain().entities_iter();
+
+    print_items(&block, query, pagination, |entity| {
+        authority_id.is_none_or(|authority_id| entity.owner == authority_id)
+            && kind.as_ref().is_none_or(|kind| entity.entity_kind == *kind)
+    })
+    .await
+}
+
+pub async fn list_authorities(
+    client: &MubertClient,
+    kind: Option<AuthorityKind>,
+    pagination: &Pagination,
+    at: &Option<At>,
+) -> Result<(), Error> {
+    let block = client.block(at).await?;
+    let query = ip_onchain_runtime::storage()
+        .ip_onchain()
+        .authorities_iter();
+
+    print_items(&block, query, pagination, |authority| {
+        kind.as_ref()
+            .is_none_or(|kind| authority.authority_kind == *kind)
+    })
+    .await
+}
+
+pub async fn list_foreign_requests(
+    client: &MubertClient,
+    authority_id: Option<u32>,
+    pending_only: bool,
+    pagination: &Pagination,
+    at: &Option<At>,
+) -> Result<(), Error> {
+    let block = client.block(at).await?;
+    let query = ip_onchain_runtime::storage()
+        .ip_onchain()
+        .foreigns_requests_iter();
+
+    print_items(&block, query, pagination, |request| {
+        authority_id.is_none_or(|authority_id| request.here_authority_id == authority_id)
+            && !(pending_only && is_answered(request.approval, request.done))
+    })
+    .await
+}
+
+/// A request is pending until it is approved or done.
+fn is_answered(approval: bool, done: bool) -> bool {
+    approval || done
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items(ids: &[u32]) -> Vec<Item<()>> {
+        ids.iter()
+            .map(|id| Item {
+                id: *id,
+                details: (),
+            })
+            .collect()
+    }
+
+    fn ids(items: Vec<Item<()>>) -> Vec<u32> {
+        items.iter().map(|item| item.id).collect()
+    }
+
+    #[test]
+    fn test_key_id() {
+        let mut key = vec![0xffu8; 48];
+        key.extend(7u32.to_le_bytes());
+        assert_eq!(key_id(&key).unwrap(), 7);
+    }
+
+    #[test]
+    fn test_paginate() {
+        let pagination = Pagination {
+            limit: Some(2),
+            start_after: Some(1),
+            sorted: true,
+        };
+        assert_eq!(ids(paginate(items(&[5, 0, 3, 1, 2]), &pagination)), [2, 3]);
+    }
+
+    #[test]
+    fn test_pending_request() {
+        assert!(!is_answered(false, false));
+        assert!(is_answered(true, false));
+        assert!(is_answered(false, true));
+    }
+
+    #[test]
+    fn test_paginate_all() {
+        let pagination = Pagination::default();
+        assert_eq!(ids(paginate(items(&[2, 0, 1]), &pagination)), [0, 1, 2]);
+    }
+}
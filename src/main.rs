@@ -1,7 +1,28 @@
 use clap::{CommandFactory, Parser, Subcommand};
+use mubert_cli::access::AccessSettings;
+use mubert_cli::arweave;
+use mubert_cli::block::At;
+use mubert_cli::config::ConfigArgs;
+use mubert_cli::dispatch::{TxFailed, TX_FAILED_EXIT_CODE};
+use mubert_cli::error::Error;
 use mubert_cli::ip_onchain_runtime::ip_onchain::calls::types::create_authority;
+use mubert_cli::ip_onchain_runtime::ip_onchain::calls::types::create_entity;
+use mubert_cli::ip_onchain_runtime::runtime_types::pallet_arweave::types::TaskState;
+use mubert_cli::journal::ResumeArgs;
+use mubert_cli::keystore::KeystoreArgs;
+use mubert_cli::list::Pagination;
+use mubert_cli::multisig::MultisigDirArgs;
+use mubert_cli::poll::{PollArgs, PollOptions, Waiting};
+use mubert_cli::signer::{Scheme, Signer, SignerArgs};
+use mubert_cli::tx::OfflineSigner;
+use mubert_cli::upload::UploadArgs;
+use mubert_cli::verify::VERIFY_FAILED_EXIT_CODE;
+use mubert_cli::watch::WatchFilter;
+use mubert_cli::worker::WorkerArgs;
+use mubert_cli::MubertClient;
+use std::time::Duration;
 
-use subxt::utils::AccountId32;
+use subxt::utils::{AccountId32, H256};
 
 #[derive(Parser)]
 #[command(
@@ -15,57 +36,140 @@ struct Cli {
     #[arg(long, default_value = "ws://127.0.0.1:9944")]
     node_url: String,
 
+    #[arg(
+        long,
+        global = true,
+        help = "block hash or block number to query the state at, latest by default"
+    )]
+    at: Option<At>,
+
+    #[arg(
+        long,
+        global = true,
+        help = "only predict the result, fee and weight of the transaction, nothing is broadcast"
+    )]
+    dry_run: bool,
+
+    #[command(flatten)]
+    config: ConfigArgs,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
 
+/// Fingerprint api token, upload and polling options of the commands uploading audio.
+#[derive(clap::Args)]
+struct FingerprintArgs {
+    #[arg(long)]
+    api_auth: String,
+    #[command(flatten)]
+    upload: UploadArgs,
+    #[command(flatten)]
+    poll: PollArgs,
+}
+
 #[derive(Subcommand)]
 enum Commands {
     UploadIP {
-        #[arg(long)]
-        api_auth: String,
+        #[command(flatten)]
+        fingerprint: FingerprintArgs,
         #[arg(short = 'f', long)]
         file: std::path::PathBuf,
         #[arg(short = 'd', long, help = "data as plain json")]
         data: Option<String>,
         #[arg(short = 'j', long)]
         data_file: Option<std::path::PathBuf>,
-        #[arg(short = 's', long)]
-        secret_key_file: Option<std::path::PathBuf>,
+        #[command(flatten)]
+        signer: SignerArgs,
         #[arg(long)]
         arweave_worker_address: Option<AccountId32>,
+        #[command(flatten)]
+        resume: ResumeArgs,
+    },
+    BatchUpload {
+        #[command(flatten)]
+        fingerprint: FingerprintArgs,
+        #[arg(
+            short = 'm',
+            long,
+            help = "json list or csv (file,data_file) of tracks"
+        )]
+        manifest: std::path::PathBuf,
+        #[arg(long, help = "progress file, <manifest>.state.json by default")]
+        state_file: Option<std::path::PathBuf>,
+        #[command(flatten)]
+        signer: SignerArgs,
+        #[arg(
+            short = 'w',
+            long,
+            default_value_t = 4,
+            help = "concurrent fingerprint jobs"
+        )]
+        workers: usize,
+    },
+    EditEntity {
+        #[arg(short = 'e', long)]
+        entity_id: u32,
+        #[arg(short = 'd', long, help = "patch as plain json")]
+        data: Option<String>,
+        #[arg(short = 'j', long)]
+        data_file: Option<std::path::PathBuf>,
+        #[command(flatten)]
+        signer: SignerArgs,
     },
     CreateAuthority {
         #[arg(short = 'n', long)]
         name: String,
         #[arg(value_enum, short = 'k', long)]
         kind: create_authority::AuthorityKind,
-        #[arg(short = 's', long)]
-        secret_key_file: Option<std::path::PathBuf>,
+        #[arg(
+            short = 'c',
+            long,
+            help = "pallet_nfts collection config as json file, the listed settings are disabled like the set bits of pallet_nfts"
+        )]
+        collection_cfg_file: Option<std::path::PathBuf>,
+        #[command(flatten)]
+        signer: SignerArgs,
+    },
+    EditAuthority {
+        #[arg(short = 'a', long)]
+        authority_id: u32,
+        #[arg(short = 'n', long)]
+        name: Option<String>,
+        #[arg(value_enum, short = 'k', long)]
+        kind: Option<create_authority::AuthorityKind>,
+        #[arg(
+            short = 'c',
+            long,
+            help = "pallet_nfts collection config as json file, the listed settings are disabled like the set bits of pallet_nfts"
+        )]
+        collection_cfg_file: Option<std::path::PathBuf>,
+        #[command(flatten)]
+        signer: SignerArgs,
     },
     ForeignRequest {
         #[arg(long, help = "data as plain json")]
         data: Option<String>,
         #[arg(short = 'j', long)]
         data_file: Option<std::path::PathBuf>,
-        #[arg(long)]
-        secret_key_file: Option<std::path::PathBuf>,
-        #[arg(short = 's', long, help = "foreign location parachain id")]
+        #[command(flatten)]
+        signer: SignerArgs,
+        #[arg(long, help = "foreign location parachain id")]
         src_parachain_id: u32,
         #[arg(short = 'd', long, help = "parachain id where source entity exists")]
         dst_parachain_id: u32,
     },
     ForeignRequestApprove {
-        #[arg(long)]
-        secret_key_file: Option<std::path::PathBuf>,
+        #[command(flatten)]
+        signer: SignerArgs,
         #[arg(short = 'e', long)]
         entity_id: u32,
         #[arg(short = 'r', long)]
         request_id: u32,
     },
     ForeignRequestTake {
-        #[arg(long)]
-        secret_key_file: Option<std::path::PathBuf>,
+        #[command(flatten)]
+        signer: SignerArgs,
         #[arg(short = 'r', long)]
         request_id: u32,
         #[arg(short = 'd', long, help = "parachain id where source entity exists")]
@@ -79,107 +183,970 @@ enum Commands {
         #[arg(short = 'e', long)]
         entity_id: u32,
     },
+    #[command(about = "check the metadata of an entity is reachable and matches the audio")]
+    VerifyEntity {
+        #[arg(short = 'e', long)]
+        entity_id: u32,
+        #[arg(
+            short = 'f',
+            long,
+            requires = "api_auth",
+            help = "audio to fingerprint again and compare with the metadata fingerprint"
+        )]
+        file: Option<std::path::PathBuf>,
+        #[arg(
+            long,
+            env = "MUBERT_ARWEAVE_READ_GATEWAY",
+            help = "gateway to read the arweave metadata from instead of https://arweave.net"
+        )]
+        gateway_url: Option<String>,
+        #[arg(long)]
+        api_auth: Option<String>,
+        #[command(flatten)]
+        upload: UploadArgs,
+        #[command(flatten)]
+        poll: PollArgs,
+    },
     GetAuthority {
         #[arg(short = 'a', long)]
         authority_id: u32,
     },
+    CreateAuthor {
+        #[arg(short = 'n', long)]
+        nickname: String,
+        #[arg(short = 'r', long)]
+        real_name: Option<String>,
+        #[arg(short = 'o', long, help = "author owner account, signer by default")]
+        owner: Option<AccountId32>,
+        #[command(flatten)]
+        signer: SignerArgs,
+    },
+    EditAuthor {
+        #[arg(short = 'a', long)]
+        author_id: u32,
+        #[arg(short = 'r', long)]
+        real_name: Option<String>,
+        #[arg(short = 'o', long)]
+        new_owner: Option<AccountId32>,
+        #[command(flatten)]
+        signer: SignerArgs,
+    },
+    GetAuthor {
+        #[arg(short = 'a', long)]
+        author_id: u32,
+    },
+    ListEntities {
+        #[arg(short = 'a', long)]
+        authority_id: Option<u32>,
+        #[arg(value_enum, short = 'k', long, ignore_case = true)]
+        kind: Option<create_entity::EntityKind>,
+        #[command(flatten)]
+        pagination: Pagination,
+    },
+    ListAuthorities {
+        #[arg(value_enum, short = 'k', long, ignore_case = true)]
+        kind: Option<create_authority::AuthorityKind>,
+        #[command(flatten)]
+        pagination: Pagination,
+    },
+    ListForeignRequests {
+        #[arg(
+            short = 'a',
+            long,
+            help = "authority id the requested entity belongs to"
+        )]
+        authority_id: Option<u32>,
+        #[arg(long, help = "only requests not approved nor done yet")]
+        pending_only: bool,
+        #[command(flatten)]
+        pagination: Pagination,
+    },
+    EntityHistory {
+        #[arg(short = 'e', long)]
+        entity_id: u32,
+        #[arg(long, default_value_t = 0)]
+        from_block: u32,
+        #[arg(long, help = "last block to scan, latest finalized by default")]
+        to_block: Option<u32>,
+        #[arg(
+            long,
+            help = "dir for the scanned blocks cache, user cache dir by default"
+        )]
+        cache_dir: Option<std::path::PathBuf>,
+    },
+    #[command(about = "follow ip_onchain and arweave events of finalized blocks")]
+    Watch {
+        #[command(flatten)]
+        filter: WatchFilter,
+        #[arg(long, help = "print json lines instead of text")]
+        json: bool,
+    },
+    #[command(subcommand, about = "manage accounts access to an authority")]
+    Access(AccessCommands),
+    #[command(subcommand, about = "tasks of the arweave workers")]
+    Arweave(ArweaveCommands),
+    #[command(subcommand, about = "run an arweave worker with the signer account")]
+    Worker(WorkerCommands),
+    #[command(
+        subcommand,
+        about = "manage the encrypted keystore, use a key with --key"
+    )]
+    Key(KeyCommands),
+    #[command(
+        subcommand,
+        about = "build, sign offline and submit a transaction in separate steps"
+    )]
+    Tx(TxCommands),
+    #[command(subcommand, about = "pending calls of --as-multisig")]
+    Multisig(MultisigCommands),
+    #[command(about = "dev: serve a local mock of the fingerprint api, use it with --api-url")]
+    MockFingerprint {
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        listen: String,
+        #[arg(
+            long,
+            help = "json with auth_token, delay_ms, pending_polls and failures [{path, status, times}]"
+        )]
+        script: Option<std::path::PathBuf>,
+    },
+    #[command(about = "dev: serve a local arweave gateway, use it with worker run --gateway-url")]
+    MockArweave {
+        #[arg(long, default_value = "127.0.0.1:1984")]
+        listen: String,
+        #[arg(
+            long,
+            default_value_t = 0,
+            help = "status requests of an upload answered pending"
+        )]
+        pending_polls: u32,
+    },
+}
+
+#[derive(Subcommand)]
+enum WorkerCommands {
+    #[command(about = "sign, upload and validate the tasks of the signer account until Ctrl-C")]
+    Run {
+        #[command(flatten)]
+        worker: WorkerArgs,
+        #[command(flatten)]
+        signer: SignerArgs,
+    },
+}
+
+#[derive(Subcommand)]
+enum MultisigCommands {
+    #[command(about = "pending calls with their approvals on chain")]
+    List {
+        #[command(flatten)]
+        dir: MultisigDirArgs,
+    },
+    #[command(about = "approve a pending call, the last approval executes it")]
+    Approve {
+        #[arg(long)]
+        call_hash: H256,
+        #[command(flatten)]
+        signer: SignerArgs,
+    },
+    #[command(about = "cancel a pending call, only its first approver can")]
+    Cancel {
+        #[arg(long)]
+        call_hash: H256,
+        #[command(flatten)]
+        signer: SignerArgs,
+    },
+}
+
+#[derive(Subcommand)]
+enum TxCommands {
+    #[command(about = "write the unsigned transaction of any command, nothing is signed")]
+    Build {
+        #[arg(long, help = "account that will sign the transaction")]
+        account_id: AccountId32,
+        #[arg(short = 'o', long)]
+        output: std::path::PathBuf,
+        #[arg(long, help = "nonce to use, the next account nonce by default")]
+        nonce: Option<u64>,
+        #[arg(
+            long,
+            default_value_t = 4096,
+            help = "blocks the transaction stays valid for, 0 for immortal"
+        )]
+        era_period: u64,
+        #[arg(
+            trailing_var_arg = true,
+            allow_hyphen_values = true,
+            required = true,
+            help = "transaction command with its args, like create-author --nickname=dev"
+        )]
+        command: Vec<String>,
+    },
+    #[command(about = "sign an unsigned transaction, works without a node")]
+    Sign {
+        #[arg(short = 'i', long)]
+        input: std::path::PathBuf,
+        #[arg(short = 'o', long)]
+        output: std::path::PathBuf,
+        #[command(flatten)]
+        signer: Box<SignerArgs>,
+    },
+    #[command(about = "submit a signed transaction and wait for its events")]
+    Submit {
+        #[arg(short = 'i', long)]
+        input: std::path::PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum KeyCommands {
+    Generate {
+        #[arg(short = 'n', long)]
+        name: String,
+        #[arg(long, value_enum, default_value_t)]
+        scheme: Scheme,
+        #[arg(
+            long,
+            default_value_t = 12,
+            help = "mnemonic words: 12, 15, 18, 21 or 24"
+        )]
+        words: usize,
+        #[command(flatten)]
+        keystore: KeystoreArgs,
+    },
+    Import {
+        #[arg(short = 'n', long)]
+        name: String,
+        #[arg(
+            long,
+            help = "secret uri: mnemonic, 0x hex seed, with optional derivation"
+        )]
+        suri: Option<String>,
+        #[arg(long, help = "polkadot.js json export")]
+        json_file: Option<std::path::PathBuf>,
+        #[arg(long, value_enum, default_value_t)]
+        scheme: Scheme,
+        #[command(flatten)]
+        keystore: KeystoreArgs,
+    },
+    List {
+        #[command(flatten)]
+        keystore: KeystoreArgs,
+    },
+    ExportPublic {
+        #[arg(short = 'n', long)]
+        name: String,
+        #[command(flatten)]
+        keystore: KeystoreArgs,
+    },
+    Remove {
+        #[arg(short = 'n', long)]
+        name: String,
+        #[command(flatten)]
+        keystore: KeystoreArgs,
+    },
+}
+
+#[derive(Subcommand)]
+enum AccessCommands {
+    Grant {
+        #[arg(short = 'a', long)]
+        authority_id: u32,
+        #[arg(long)]
+        account_id: AccountId32,
+        #[command(flatten)]
+        access: AccessSettings,
+        #[command(flatten)]
+        signer: SignerArgs,
+    },
+    Edit {
+        #[arg(short = 'a', long)]
+        authority_id: u32,
+        #[arg(long)]
+        account_id: AccountId32,
+        #[command(flatten)]
+        access: AccessSettings,
+        #[command(flatten)]
+        signer: SignerArgs,
+    },
+    Show {
+        #[arg(short = 'a', long)]
+        authority_id: u32,
+        #[arg(long)]
+        account_id: AccountId32,
+    },
+    List {
+        #[arg(short = 'a', long)]
+        authority_id: u32,
+    },
+}
+
+#[derive(Subcommand)]
+enum ArweaveCommands {
+    CreateTask {
+        #[arg(long)]
+        worker_address: AccountId32,
+        #[arg(long, conflicts_with = "data_file", help = "payload to upload")]
+        data: Option<String>,
+        #[arg(short = 'j', long, help = "file with the payload to upload")]
+        data_file: Option<std::path::PathBuf>,
+        #[arg(long, default_value_t = arweave::DEFAULT_AMOUNT)]
+        amount: u128,
+        #[arg(long, default_value_t = arweave::DEFAULT_TIPS)]
+        tips: u128,
+        #[command(flatten)]
+        signer: SignerArgs,
+    },
+    GetTask {
+        #[arg(short = 't', long)]
+        task_id: u64,
+    },
+    ListTasks {
+        #[arg(long, help = "only tasks of this worker")]
+        worker: Option<AccountId32>,
+        #[arg(value_enum, long, ignore_case = true)]
+        state: Option<TaskState>,
+        #[command(flatten)]
+        pagination: Pagination,
+    },
+    ListWorkers,
+    UpdateTask {
+        #[arg(short = 't', long)]
+        task_id: u64,
+        #[arg(value_enum, long, ignore_case = true)]
+        state: TaskState,
+        #[command(flatten)]
+        signer: SignerArgs,
+    },
+    SignTask {
+        #[arg(short = 't', long)]
+        task_id: u64,
+        #[arg(long, help = "hex of the data signed by the worker")]
+        signed_data: String,
+        #[arg(long, help = "arweave tx hash of the upload")]
+        tx_hash: Option<String>,
+        #[command(flatten)]
+        signer: SignerArgs,
+    },
+    ClearTask {
+        #[arg(short = 't', long)]
+        task_id: u64,
+        #[command(flatten)]
+        signer: SignerArgs,
+    },
+}
+
+impl Cli {
+    /// Client of the command, the signer is only given to the transaction commands
+    /// and the fingerprint api options to the commands uploading audio.
+    async fn client(
+        &self,
+        signer: Option<Signer>,
+        fingerprint: Option<&FingerprintArgs>,
+    ) -> Result<MubertClient, Error> {
+        let mut builder = MubertClient::builder().node_url(&self.node_url);
+        if let Some(api_url) = self.config.api_url()? {
+            builder = builder.api_url(api_url);
+        }
+        if let Some(signer) = signer {
+            builder = builder.signer(signer);
+        }
+        if let Some(fingerprint) = fingerprint {
+            let poll = &fingerprint.poll;
+            builder = builder
+                .auth_token(&fingerprint.api_auth)
+                .upload_options(fingerprint.upload.options()?)
+                .fingerprint_poll(poll.options(PollOptions::every(Duration::from_secs(10))))
+                .arweave_poll(poll.options(PollOptions::every(Duration::from_secs(6))));
+        }
+        builder.build().await
+    }
+}
+
+/// Client of a transaction command with its signer.
+async fn tx_client(
+    cli: &Cli,
+    args: &SignerArgs,
+    offline: Option<&OfflineSigner>,
+) -> Result<MubertClient, Error> {
+    cli.client(Some(signer_for(args, offline, cli.dry_run)?), None)
+        .await
+}
+
+/// Signer of the command, `tx build` replaces its key with the offline signer.
+fn signer_for(
+    args: &SignerArgs,
+    offline: Option<&OfflineSigner>,
+    dry_run: bool,
+) -> Result<Signer, Error> {
+    match (offline, dry_run) {
+        (Some(_), true) => Err(Error::Config(
+            "--dry-run can not be used with tx build".into(),
+        )),
+        (Some(offline), false) => args.offline_signer(offline),
+        (None, true) => args.dry_run_signer(),
+        (None, false) => args.signer(),
+    }
 }
 
 #[tokio::main(flavor = "current_thread")]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let cli = Cli::parse();
+async fn main() {
+    let mut cli = Cli::parse();
+
+    let mut offline = None;
+    if let Some(Commands::Tx(TxCommands::Build {
+        account_id,
+        output,
+        nonce,
+        era_period,
+        command,
+    })) = &cli.command
+    {
+        offline = Some(OfflineSigner {
+            account_id: account_id.clone(),
+            output: output.clone(),
+            nonce: *nonce,
+            era_period: *era_period,
+        });
+        let mut args: Vec<String> = vec!["mubert-cli".into(), "--node-url".into()];
+        args.push(cli.node_url.clone());
+        if let Some(api_url) = &cli.config.api_url {
+            args.extend(["--api-url".into(), api_url.clone()]);
+        }
+        if let Some(config) = &cli.config.config {
+            args.extend(["--config".into(), config.display().to_string()]);
+        }
+        args.extend(command.iter().cloned());
+        cli = Cli::try_parse_from(args).unwrap_or_else(|e| e.exit());
+    }
 
-    if cli.command.is_some() {
-        match &cli.command {
-            Some(Commands::UploadIP {
-                api_auth,
+    let command = run(&cli, cli.command.as_ref(), offline.as_ref());
+    let result = if handles_ctrl_c(cli.command.as_ref()) {
+        command.await
+    } else {
+        // Ctrl-C while polling stops with Error::Cancelled, anywhere else it just exits
+        tokio::pin!(command);
+        tokio::select! {
+            biased;
+            result = &mut command => result,
+            _ = tokio::signal::ctrl_c() => {
+                match tokio::time::timeout(CANCEL_GRACE, command).await {
+                    Ok(result) => result,
+                    Err(_) => {
+                        eprintln!("Error: interrupted");
+                        std::process::exit(INTERRUPTED_EXIT_CODE);
+                    }
+                }
+            }
+        }
+    };
+
+    match result {
+        Ok(()) => {}
+        Err(Error::Cancelled(waiting)) => {
+            eprintln!("Error: stopped waiting for {waiting}");
+            eprintln!("{}", resume_hint(&waiting));
+            std::process::exit(INTERRUPTED_EXIT_CODE);
+        }
+        Err(e @ Error::Verification(_)) => {
+            eprintln!("Error: {e}");
+            std::process::exit(VERIFY_FAILED_EXIT_CODE);
+        }
+        Err(Error::Dispatch(failed)) => {
+            eprintln!("Error: {failed}");
+            exit_tx_failed(&failed);
+        }
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Exit code of a Ctrl-C, like the shells.
+const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+/// How long a command gets after Ctrl-C to stop by itself, a polling command with `Error::Cancelled`.
+const CANCEL_GRACE: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// Whether the command stops by itself on Ctrl-C and must not be cut short,
+/// the worker prints its metrics and keeps its state file.
+fn handles_ctrl_c(command: Option<&Commands>) -> bool {
+    matches!(command, Some(Commands::Worker(WorkerCommands::Run { .. })))
+}
+
+/// How to get the result of the job the command stopped waiting for.
+fn resume_hint(waiting: &Waiting) -> String {
+    match waiting {
+        Waiting::FingerprintJob(id) => format!(
+            "The job keeps running, resume upload-ip with --resume-fingerprint-job={id} \
+             (or run it again, the journal has the job id), batch-upload by running it again"
+        ),
+        Waiting::ArweaveTask(id) => format!(
+            "The task keeps running on chain, resume upload-ip with --resume-arweave-task={id} \
+             (or run it again, the journal has the task id)"
+        ),
+    }
+}
+
+/// Failed transactions exit with their own code, the error code is for scripts.
+fn exit_tx_failed(failed: &TxFailed) -> ! {
+    eprintln!("Error code: {}", failed.code);
+    std::process::exit(TX_FAILED_EXIT_CODE)
+}
+
+async fn run(
+    cli: &Cli,
+    command: Option<&Commands>,
+    offline: Option<&OfflineSigner>,
+) -> Result<(), Error> {
+    match command {
+        Some(Commands::UploadIP {
+            fingerprint,
+            file,
+            data,
+            data_file,
+            signer,
+            arweave_worker_address,
+            resume,
+        }) => {
+            let client = cli
+                .client(
+                    Some(signer_for(signer, offline, cli.dry_run)?),
+                    Some(fingerprint),
+                )
+                .await?;
+            mubert_cli::update_ip::update_ip(
+                &client,
                 file,
                 data,
                 data_file,
-                secret_key_file,
                 arweave_worker_address,
-            }) => {
-                mubert_cli::update_ip::update_ip(
-                    &cli.node_url,
-                    api_auth,
-                    file,
-                    data,
-                    data_file,
-                    secret_key_file,
-                    arweave_worker_address,
-                )
-                .await?;
+                resume,
+            )
+            .await?;
+        }
+        Some(Commands::BatchUpload {
+            fingerprint,
+            manifest,
+            state_file,
+            signer,
+            workers,
+        }) => {
+            if offline.is_some() || cli.dry_run {
+                return Err(Error::Config(
+                    "batch-upload sends many transactions, it can not be built or dry run".into(),
+                ));
             }
-            Some(Commands::CreateAuthority {
-                name,
-                kind,
-                secret_key_file,
-            }) => {
-                mubert_cli::create_authority::create_authority(
-                    &cli.node_url,
-                    name,
-                    kind.clone(),
-                    secret_key_file,
-                )
+            let client = cli
+                .client(Some(signer.signer()?), Some(fingerprint))
                 .await?;
-            }
-            Some(Commands::ForeignRequest {
+            mubert_cli::batch::batch_upload(&client, manifest, state_file, *workers).await?;
+        }
+        Some(Commands::EditEntity {
+            entity_id,
+            data,
+            data_file,
+            signer,
+        }) => {
+            mubert_cli::update_ip::edit_entity(
+                &tx_client(cli, signer, offline).await?,
+                *entity_id,
                 data,
                 data_file,
-                secret_key_file,
-                src_parachain_id,
-                dst_parachain_id,
-            }) => {
-                mubert_cli::foreign_request::foreign_request_to(
-                    &cli.node_url,
-                    data,
-                    data_file,
-                    secret_key_file,
-                    *src_parachain_id,
-                    *dst_parachain_id,
-                )
+            )
+            .await?;
+        }
+        Some(Commands::CreateAuthority {
+            name,
+            kind,
+            collection_cfg_file,
+            signer,
+        }) => {
+            mubert_cli::create_authority::create_authority(
+                &tx_client(cli, signer, offline).await?,
+                name,
+                kind.clone(),
+                collection_cfg_file,
+            )
+            .await?;
+        }
+        Some(Commands::EditAuthority {
+            authority_id,
+            name,
+            kind,
+            collection_cfg_file,
+            signer,
+        }) => {
+            mubert_cli::create_authority::edit_authority(
+                &tx_client(cli, signer, offline).await?,
+                *authority_id,
+                name,
+                kind.clone(),
+                collection_cfg_file,
+            )
+            .await?;
+        }
+        Some(Commands::ForeignRequest {
+            data,
+            data_file,
+            signer,
+            src_parachain_id,
+            dst_parachain_id,
+        }) => {
+            mubert_cli::foreign_request::foreign_request_to(
+                &tx_client(cli, signer, offline).await?,
+                data,
+                data_file,
+                *src_parachain_id,
+                *dst_parachain_id,
+            )
+            .await?;
+        }
+        Some(Commands::ForeignRequestApprove {
+            signer,
+            entity_id,
+            request_id,
+        }) => {
+            mubert_cli::foreign_request::foreign_request_approve(
+                &tx_client(cli, signer, offline).await?,
+                *entity_id,
+                *request_id,
+            )
+            .await?;
+        }
+        Some(Commands::ForeignRequestTake {
+            signer,
+            request_id,
+            dst_parachain_id,
+        }) => {
+            mubert_cli::foreign_request::foreign_request_take(
+                &tx_client(cli, signer, offline).await?,
+                *request_id,
+                *dst_parachain_id,
+            )
+            .await?;
+        }
+        Some(Commands::GetForeignRequest { request_id }) => {
+            mubert_cli::foreign_request::foreign_request(
+                &cli.client(None, None).await?,
+                *request_id,
+                &cli.at,
+            )
+            .await?;
+        }
+        Some(Commands::GetEntity { entity_id }) => {
+            mubert_cli::update_ip::get_entity(&cli.client(None, None).await?, *entity_id, &cli.at)
                 .await?;
-            }
-            Some(Commands::ForeignRequestApprove {
-                secret_key_file,
-                entity_id,
-                request_id,
-            }) => {
-                mubert_cli::foreign_request::foreign_request_approve(
-                    &cli.node_url,
-                    secret_key_file,
-                    *entity_id,
-                    *request_id,
-                )
+        }
+        Some(Commands::VerifyEntity {
+            entity_id,
+            file,
+            gateway_url,
+            api_auth,
+            upload,
+            poll,
+        }) => {
+            // the api is only called to fingerprint the file again
+            let fingerprint = api_auth.as_ref().map(|api_auth| FingerprintArgs {
+                api_auth: api_auth.clone(),
+                upload: upload.clone(),
+                poll: poll.clone(),
+            });
+            mubert_cli::verify::verify_entity(
+                &cli.client(None, fingerprint.as_ref()).await?,
+                *entity_id,
+                gateway_url,
+                file,
+                &cli.at,
+            )
+            .await?;
+        }
+        Some(Commands::GetAuthority { authority_id }) => {
+            mubert_cli::create_authority::get_authority(
+                &cli.client(None, None).await?,
+                *authority_id,
+                &cli.at,
+            )
+            .await?;
+        }
+        Some(Commands::CreateAuthor {
+            nickname,
+            real_name,
+            owner,
+            signer,
+        }) => {
+            mubert_cli::author::create_author(
+                &tx_client(cli, signer, offline).await?,
+                nickname,
+                real_name,
+                owner,
+            )
+            .await?;
+        }
+        Some(Commands::EditAuthor {
+            author_id,
+            real_name,
+            new_owner,
+            signer,
+        }) => {
+            mubert_cli::author::edit_author(
+                &tx_client(cli, signer, offline).await?,
+                *author_id,
+                real_name,
+                new_owner,
+            )
+            .await?;
+        }
+        Some(Commands::GetAuthor { author_id }) => {
+            mubert_cli::author::get_author(&cli.client(None, None).await?, *author_id, &cli.at)
                 .await?;
+        }
+        Some(Commands::ListEntities {
+            authority_id,
+            kind,
+            pagination,
+        }) => {
+            mubert_cli::list::list_entities(
+                &cli.client(None, None).await?,
+                *authority_id,
+                kind.clone(),
+                pagination,
+                &cli.at,
+            )
+            .await?;
+        }
+        Some(Commands::ListAuthorities { kind, pagination }) => {
+            mubert_cli::list::list_authorities(
+                &cli.client(None, None).await?,
+                kind.clone(),
+                pagination,
+                &cli.at,
+            )
+            .await?;
+        }
+        Some(Commands::ListForeignRequests {
+            authority_id,
+            pending_only,
+            pagination,
+        }) => {
+            mubert_cli::list::list_foreign_requests(
+                &cli.client(None, None).await?,
+                *authority_id,
+                *pending_only,
+                pagination,
+                &cli.at,
+            )
+            .await?;
+        }
+        Some(Commands::EntityHistory {
+            entity_id,
+            from_block,
+            to_block,
+            cache_dir,
+        }) => {
+            mubert_cli::history::entity_history(
+                &cli.client(None, None).await?,
+                *entity_id,
+                *from_block,
+                *to_block,
+                cache_dir,
+            )
+            .await?;
+        }
+        Some(Commands::Watch { filter, json }) => {
+            mubert_cli::watch::watch(&cli.client(None, None).await?, filter, *json).await?;
+        }
+        Some(Commands::Access(AccessCommands::Grant {
+            authority_id,
+            account_id,
+            access,
+            signer,
+        })) => {
+            mubert_cli::access::create_account_access(
+                &tx_client(cli, signer, offline).await?,
+                *authority_id,
+                account_id,
+                access,
+            )
+            .await?;
+        }
+        Some(Commands::Access(AccessCommands::Edit {
+            authority_id,
+            account_id,
+            access,
+            signer,
+        })) => {
+            mubert_cli::access::edit_account_access(
+                &tx_client(cli, signer, offline).await?,
+                *authority_id,
+                account_id,
+                access,
+            )
+            .await?;
+        }
+        Some(Commands::Access(AccessCommands::Show {
+            authority_id,
+            account_id,
+        })) => {
+            mubert_cli::access::get_account_access(
+                &cli.client(None, None).await?,
+                *authority_id,
+                account_id,
+                &cli.at,
+            )
+            .await?;
+        }
+        Some(Commands::Access(AccessCommands::List { authority_id })) => {
+            mubert_cli::access::list_account_access(
+                &cli.client(None, None).await?,
+                *authority_id,
+                &cli.at,
+            )
+            .await?;
+        }
+        Some(Commands::Arweave(ArweaveCommands::CreateTask {
+            worker_address,
+            data,
+            data_file,
+            amount,
+            tips,
+            signer,
+        })) => {
+            arweave::create_task(
+                &tx_client(cli, signer, offline).await?,
+                worker_address,
+                data,
+                data_file,
+                *amount,
+                *tips,
+            )
+            .await?;
+        }
+        Some(Commands::Arweave(ArweaveCommands::GetTask { task_id })) => {
+            arweave::get_task(&cli.client(None, None).await?, *task_id, &cli.at).await?;
+        }
+        Some(Commands::Arweave(ArweaveCommands::ListTasks {
+            worker,
+            state,
+            pagination,
+        })) => {
+            arweave::list_tasks(
+                &cli.client(None, None).await?,
+                worker,
+                state,
+                pagination,
+                &cli.at,
+            )
+            .await?;
+        }
+        Some(Commands::Arweave(ArweaveCommands::ListWorkers)) => {
+            arweave::list_workers(&cli.client(None, None).await?, &cli.at).await?;
+        }
+        Some(Commands::Arweave(ArweaveCommands::UpdateTask {
+            task_id,
+            state,
+            signer,
+        })) => {
+            arweave::update_task(&tx_client(cli, signer, offline).await?, *task_id, state).await?;
+        }
+        Some(Commands::Arweave(ArweaveCommands::SignTask {
+            task_id,
+            signed_data,
+            tx_hash,
+            signer,
+        })) => {
+            arweave::sign_task(
+                &tx_client(cli, signer, offline).await?,
+                *task_id,
+                signed_data,
+                tx_hash,
+            )
+            .await?;
+        }
+        Some(Commands::Arweave(ArweaveCommands::ClearTask { task_id, signer })) => {
+            arweave::clear_task(&tx_client(cli, signer, offline).await?, *task_id).await?;
+        }
+        Some(Commands::Worker(WorkerCommands::Run { worker, signer })) => {
+            if offline.is_some() || cli.dry_run {
+                return Err(Error::Config(
+                    "worker run can not be used with --dry-run or tx build".into(),
+                ));
             }
-            Some(Commands::ForeignRequestTake {
-                secret_key_file,
-                request_id,
-                dst_parachain_id,
-            }) => {
-                mubert_cli::foreign_request::foreign_request_take(
-                    &cli.node_url,
-                    secret_key_file,
-                    *request_id,
-                    *dst_parachain_id,
-                )
+            mubert_cli::worker::run(&cli.client(Some(signer.signer()?), None).await?, worker)
                 .await?;
+        }
+        Some(Commands::Key(KeyCommands::Generate {
+            name,
+            scheme,
+            words,
+            keystore,
+        })) => {
+            mubert_cli::keystore::key_generate(keystore, name, *scheme, *words)?;
+        }
+        Some(Commands::Key(KeyCommands::Import {
+            name,
+            suri,
+            json_file,
+            scheme,
+            keystore,
+        })) => {
+            mubert_cli::keystore::key_import(keystore, name, suri, json_file, *scheme)?;
+        }
+        Some(Commands::Key(KeyCommands::List { keystore })) => {
+            mubert_cli::keystore::key_list(keystore)?;
+        }
+        Some(Commands::Key(KeyCommands::ExportPublic { name, keystore })) => {
+            mubert_cli::keystore::key_export_public(keystore, name)?;
+        }
+        Some(Commands::Key(KeyCommands::Remove { name, keystore })) => {
+            mubert_cli::keystore::key_remove(keystore, name)?;
+        }
+        Some(Commands::Tx(TxCommands::Build { .. })) => {
+            return Err(Error::Config("tx build can not be nested".into()));
+        }
+        Some(Commands::Tx(TxCommands::Sign {
+            input,
+            output,
+            signer,
+        })) => {
+            mubert_cli::tx::tx_sign(input, output, &signer.signer()?)?;
+        }
+        Some(Commands::Tx(TxCommands::Submit { input })) => {
+            if offline.is_some() {
+                return Err(Error::Config("tx submit can not be built".into()));
             }
-            Some(Commands::GetForeignRequest { request_id }) => {
-                mubert_cli::foreign_request::foreign_request(&cli.node_url, *request_id).await?;
-            }
-            Some(Commands::GetEntity { entity_id }) => {
-                mubert_cli::update_ip::get_entity(&cli.node_url, *entity_id).await?;
-            }
-            Some(Commands::GetAuthority { authority_id }) => {
-                mubert_cli::create_authority::get_authority(&cli.node_url, *authority_id).await?;
-            }
-            None => {
-                Cli::command().print_help().unwrap();
-            }
-        };
-    }
-
+            mubert_cli::tx::tx_submit(&cli.client(None, None).await?, input, cli.dry_run).await?;
+        }
+        Some(Commands::Multisig(MultisigCommands::List { dir })) => {
+            mubert_cli::multisig::multisig_list(&cli.client(None, None).await?, dir).await?;
+        }
+        Some(Commands::Multisig(MultisigCommands::Approve { call_hash, signer })) => {
+            mubert_cli::multisig::multisig_approve(
+                &tx_client(cli, signer, offline).await?,
+                &signer.wrap.dir,
+                call_hash,
+            )
+            .await?;
+        }
+        Some(Commands::Multisig(MultisigCommands::Cancel { call_hash, signer })) => {
+            mubert_cli::multisig::multisig_cancel(
+                &tx_client(cli, signer, offline).await?,
+                &signer.wrap.dir,
+                call_hash,
+            )
+            .await?;
+        }
+        Some(Commands::MockFingerprint { listen, script }) => {
+            mubert_cli::mock_fingerprint::mock_fingerprint(listen, script).await?;
+        }
+        Some(Commands::MockArweave {
+            listen,
+            pending_polls,
+        }) => {
+            mubert_cli::mock_arweave::mock_arweave(listen, *pending_polls).await?;
+        }
+        None => {
+            Cli::command().print_help().unwrap();
+        }
+    };
     Ok(())
 }
@@ -0,0 +1,147 @@
+use crate::error::Error;
+use crate::mock_fingerprint::{read_request, respond, Request};
+use base64::Engine;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+/// An upload received by the mock gateway.
+#[derive(Clone, Debug)]
+pub struct MockUpload {
+    pub id: String,
+    pub data: Vec<u8>,
+    /// The `x-signature` header.
+    pub signature: Option<String>,
+}
+
+#[derive(Default)]
+struct MockState {
+    pending_polls: u32,
+    uploads: Vec<MockUpload>,
+    status_polls: HashMap<String, u32>,
+}
+
+/// Local arweave gateway with `POST /tx`, `GET /tx/{id}/status` and `GET /{id}`,
+/// to run the worker without uploading to arweave. The tx id is the base64url sha256
+/// of the data, like the 43 characters of an arweave id.
+pub struct MockArweave {
+    addr: SocketAddr,
+    state: Arc<Mutex<MockState>>,
+    task: JoinHandle<()>,
+}
+
+impl MockArweave {
+    /// Serves on `addr` until dropped, an upload is `Pending` for its first `pending_polls`
+    /// status requests.
+    pub async fn start(addr: &str, pending_polls: u32) -> Result<Self, Error> {
+        let listener = TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(MockState {
+            pending_polls,
+            ..Default::default()
+        }));
+
+        let conn_state = state.clone();
+        let task = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(stream, conn_state.clone()));
+            }
+        });
+
+        Ok(MockArweave { addr, state, task })
+    }
+
+    /// Base url to give to `--gateway-url`.
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    pub fn uploads(&self) -> Vec<MockUpload> {
+        self.lock().uploads.clone()
+    }
+
+    /// Drops the upload, like a tx the gateway never mined.
+    pub fn forget(&self, id: &str) {
+        let mut state = self.lock();
+        state.uploads.retain(|upload| upload.id != id);
+        state.status_polls.remove(id);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for MockArweave {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn serve(mut stream: TcpStream, state: Arc<Mutex<MockState>>) {
+    let (status, body) = match read_request(&mut stream).await {
+        Ok(request) => {
+            let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+            route(&mut state, request)
+        }
+        Err(e) => (400, e.to_string()),
+    };
+    respond(&mut stream, status, &body).await;
+}
+
+fn route(state: &mut MockState, request: Request) -> (u16, String) {
+    let path = request.path.trim_start_matches('/').to_string();
+    let segments: Vec<&str> = path.split('/').collect();
+    match (request.method.as_str(), segments.as_slice()) {
+        ("POST", ["tx"]) => {
+            let id = base64::engine::general_purpose::URL_SAFE_NO_PAD
+                .encode(Sha256::digest(&request.body));
+            if !state.uploads.iter().any(|upload| upload.id == id) {
+                state.uploads.push(MockUpload {
+                    id: id.clone(),
+                    data: request.body,
+                    signature: request.headers.get("x-signature").cloned(),
+                });
+                state.status_polls.insert(id.clone(), 0);
+            }
+            (200, json!({ "id": id }).to_string())
+        }
+        ("GET", ["tx", id, "status"]) => {
+            let pending_polls = state.pending_polls;
+            let Some(polls) = state.status_polls.get_mut(*id) else {
+                return (404, "Not Found".into());
+            };
+            *polls += 1;
+            if *polls > pending_polls {
+                let confirmations = *polls - pending_polls;
+                (
+                    200,
+                    json!({ "block_height": 1, "number_of_confirmations": confirmations })
+                        .to_string(),
+                )
+            } else {
+                (202, "Pending".into())
+            }
+        }
+        ("GET", [id]) => match state.uploads.iter().find(|upload| upload.id == *id) {
+            Some(upload) => (200, String::from_utf8_lossy(&upload.data).to_string()),
+            None => (404, "Not Found".into()),
+        },
+        _ => (
+            404,
+            format!("{} {} not found", request.method, request.path),
+        ),
+    }
+}
+
+/// Runs the mock until killed.
+pub async fn mock_arweave(listen: &str, pending_polls: u32) -> Result<(), Error> {
+    let mock = MockArweave::start(listen, pending_polls).await?;
+    println!("mock arweave gateway listening on {}", mock.url());
+    futures::future::pending::<()>().await;
+    Ok(())
+}
@@ -0,0 +1,432 @@
+use crate::error::Error;
+use serde::Deserialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+/// Behaviour of the mock fingerprint api, read from a json file by `mock-fingerprint`.
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct MockScript {
+    /// Bearer token the requests must have, any one is accepted if not set.
+    pub auth_token: Option<String>,
+    /// Delay before every answer.
+    pub delay_ms: u64,
+    /// Status requests of a job answered `processing` before it is `done`.
+    pub pending_polls: u32,
+    /// The jobs end `failed` instead of `done`.
+    pub failed_jobs: bool,
+    /// Serves the resumable upload sessions `/v1/fingerprint/upload`.
+    pub resumable: bool,
+    pub failures: Vec<MockFailure>,
+}
+
+/// The first `times` requests to a path starting with `path` fail with `status`,
+/// or with a closed connection if `drop` is set. All of them fail if `times` is not set.
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct MockFailure {
+    pub path: String,
+    /// Only the requests of this method fail, all of them if not set.
+    pub method: Option<String>,
+    pub status: u16,
+    pub drop: bool,
+    pub times: Option<u32>,
+    pub body: String,
+}
+
+/// What the mock received, for the tests to check.
+#[derive(Clone, Debug, Default)]
+pub struct MockRecord {
+    /// Size of every uploaded audio.
+    pub uploads: Vec<usize>,
+    /// Hex sha256 of every uploaded audio.
+    pub uploads_sha256: Vec<String>,
+    /// Chunks received by the upload sessions.
+    pub chunks: u32,
+    pub status_polls: u32,
+    pub metadata: Vec<serde_json::Value>,
+}
+
+struct MockState {
+    script: MockScript,
+    url: String,
+    record: MockRecord,
+    jobs_polls: HashMap<String, u32>,
+    sessions: Vec<UploadSession>,
+    failures_left: Vec<Option<u32>>,
+}
+
+struct UploadSession {
+    sha256: String,
+    size: u64,
+    data: Vec<u8>,
+    job: Option<String>,
+}
+
+impl MockState {
+    /// New job of the fully received audio.
+    fn add_job(&mut self, data: &[u8]) -> String {
+        self.record.uploads.push(data.len());
+        self.record
+            .uploads_sha256
+            .push(hex::encode(Sha256::digest(data)));
+        let id = format!("job-{}", self.record.uploads.len());
+        self.jobs_polls.insert(id.clone(), 0);
+        id
+    }
+}
+
+pub(crate) struct Request {
+    pub(crate) method: String,
+    pub(crate) path: String,
+    pub(crate) query: HashMap<String, String>,
+    pub(crate) headers: HashMap<String, String>,
+    pub(crate) body: Vec<u8>,
+}
+
+/// Local fingerprint api with `/v1/fingerprint/create`, `/v1/fingerprint/status`,
+/// `/v1/metadata/create` and optionally the `/v1/fingerprint/upload` sessions,
+/// to run the off chain flow without the real service. The fingerprint and metadata
+/// urls it answers are served too, a fingerprint is the sha256 of the audio.
+pub struct MockFingerprint {
+    addr: SocketAddr,
+    state: Arc<Mutex<MockState>>,
+    task: JoinHandle<()>,
+}
+
+impl MockFingerprint {
+    /// Serves on `addr` until dropped, `127.0.0.1:0` picks a free port.
+    pub async fn start(addr: &str, script: MockScript) -> Result<Self, Error> {
+        let listener = TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(MockState {
+            failures_left: script.failures.iter().map(|f| f.times).collect(),
+            script,
+            url: format!("http://{addr}"),
+            record: MockRecord::default(),
+            jobs_polls: HashMap::new(),
+            sessions: Vec::new(),
+        }));
+
+        let conn_state = state.clone();
+        let task = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(stream, conn_state.clone()));
+            }
+        });
+
+        Ok(MockFingerprint { addr, state, task })
+    }
+
+    /// Base url to give to `--api-url`.
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    pub fn record(&self) -> MockRecord {
+        self.lock().record.clone()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for MockFingerprint {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn serve(mut stream: TcpStream, state: Arc<Mutex<MockState>>) {
+    let answer = match read_request(&mut stream).await {
+        Ok(request) => {
+            let delay = {
+                let state = state.lock().unwrap_or_else(|e| e.into_inner());
+                Duration::from_millis(state.script.delay_ms)
+            };
+            tokio::time::sleep(delay).await;
+            let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+            answer(&mut state, request)
+        }
+        Err(e) => Some((400, e.to_string())),
+    };
+    if let Some((status, body)) = answer {
+        respond(&mut stream, status, &body).await;
+    }
+}
+
+/// Writes the json answer and closes the connection.
+pub(crate) async fn respond(stream: &mut TcpStream, status: u16, body: &str) {
+    let response = format!(
+        "HTTP/1.1 {status} {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+        if status < 400 { "OK" } else { "Error" },
+        body.len()
+    );
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+pub(crate) async fn read_request(stream: &mut TcpStream) -> Result<Request, Error> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 8192];
+    let head_end = loop {
+        if let Some(end) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            break end;
+        }
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            return Err(Error::Decode("connection closed before the headers".into()));
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..head_end]).to_string();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split(' ');
+    let method = request_line.next().unwrap_or_default().to_string();
+    let target = request_line.next().unwrap_or_default();
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+    let headers: HashMap<String, String> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(k, v)| (k.trim().to_lowercase(), v.trim().to_string()))
+        .collect();
+
+    let length: usize = headers
+        .get("content-length")
+        .map(|l| l.parse())
+        .transpose()
+        .map_err(|e| Error::Decode(format!("content-length: {e}")))?
+        .unwrap_or(0);
+    let mut body = buffer[head_end + 4..].to_vec();
+    while body.len() < length {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            return Err(Error::Decode("connection closed before the body".into()));
+        }
+        body.extend_from_slice(&chunk[..read]);
+    }
+
+    Ok(Request {
+        method,
+        path: path.to_string(),
+        query: query
+            .split('&')
+            .filter_map(|p| p.split_once('='))
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+        headers,
+        body,
+    })
+}
+
+/// Status and body of the answer, `None` closes the connection without answering.
+fn answer(state: &mut MockState, request: Request) -> Option<(u16, String)> {
+    // the fingerprint and metadata documents are public
+    if let Some(token) = state
+        .script
+        .auth_token
+        .as_ref()
+        .filter(|_| request.path.starts_with("/v1/"))
+    {
+        if request.headers.get("authorization") != Some(&format!("Bearer {token}")) {
+            return Some((401, "bad auth token".into()));
+        }
+    }
+
+    let failure = state
+        .script
+        .failures
+        .iter()
+        .zip(state.failures_left.iter_mut())
+        .find(|(f, left)| {
+            request.path.starts_with(&f.path)
+                && f.method.as_ref().is_none_or(|m| *m == request.method)
+                && left.is_none_or(|left| left > 0)
+        });
+    if let Some((failure, left)) = failure {
+        if let Some(left) = left {
+            *left -= 1;
+        }
+        return (!failure.drop).then(|| (failure.status, failure.body.clone()));
+    }
+
+    Some(route(state, request))
+}
+
+fn route(state: &mut MockState, request: Request) -> (u16, String) {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("PUT", "/v1/fingerprint/create") => {
+            let id = state.add_job(&request.body);
+            (200, json!({ "id": id }).to_string())
+        }
+        ("POST", "/v1/fingerprint/upload") if state.script.resumable => {
+            upload_session(state, &request.body)
+        }
+        ("PUT", path) if state.script.resumable && path.starts_with("/v1/fingerprint/upload/") => {
+            upload_chunk(state, &path["/v1/fingerprint/upload/".len()..], &request)
+        }
+        ("GET", "/v1/fingerprint/status") => {
+            state.record.status_polls += 1;
+            let id = request.query.get("task_id").cloned().unwrap_or_default();
+            let Some(polls) = state.jobs_polls.get_mut(&id) else {
+                return (404, format!("job {id} not found"));
+            };
+            *polls += 1;
+            let (status, url) = if *polls <= state.script.pending_polls {
+                ("processing", String::new())
+            } else if state.script.failed_jobs {
+                ("failed", String::new())
+            } else {
+                ("done", format!("{}/fingerprint/{id}", state.url))
+            };
+            (
+                200,
+                json!({ "id": id, "status": status, "url": url }).to_string(),
+            )
+        }
+        ("POST", "/v1/metadata/create") => {
+            match serde_json::from_slice::<serde_json::Value>(&request.body) {
+                Ok(metadata) => {
+                    state.record.metadata.push(metadata);
+                    let url = format!("{}/metadata/{}", state.url, state.record.metadata.len());
+                    (200, json!({ "url": url }).to_string())
+                }
+                Err(e) => (400, format!("bad metadata json: {e}")),
+            }
+        }
+        ("GET", path) if path.starts_with("/fingerprint/job-") => {
+            let sha256 = path["/fingerprint/job-".len()..]
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| state.record.uploads_sha256.get(i.wrapping_sub(1)));
+            match sha256 {
+                Some(sha256) => (200, json!({ "sha256": sha256 }).to_string()),
+                None => (404, format!("fingerprint {path} not found")),
+            }
+        }
+        ("GET", path) if path.starts_with("/metadata/") => {
+            let metadata = path["/metadata/".len()..]
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| state.record.metadata.get(i.wrapping_sub(1)));
+            match metadata {
+                Some(metadata) => (200, metadata.to_string()),
+                None => (404, format!("metadata {path} not found")),
+            }
+        }
+        _ => (
+            404,
+            format!("{} {} not found", request.method, request.path),
+        ),
+    }
+}
+
+/// Runs the mock until killed, `script` is a json `MockScript`.
+pub async fn mock_fingerprint(listen: &str, script: &Option<PathBuf>) -> Result<(), Error> {
+    let script = match script {
+        Some(path) => {
+            let data = std::fs::read_to_string(path)
+                .map_err(|e| Error::Config(format!("read script {path:?}: {e}")))?;
+            serde_json::from_str(&data)
+                .map_err(|e| Error::Config(format!("parsing script {path:?}: {e}")))?
+        }
+        None => MockScript::default(),
+    };
+
+    let mock = MockFingerprint::start(listen, script).await?;
+    println!("mock fingerprint api listening on {}", mock.url());
+    futures::future::pending::<()>().await;
+    Ok(())
+}
+
+/// Session of the content hash, a new one or the one to continue.
+fn upload_session(state: &mut MockState, body: &[u8]) -> (u16, String) {
+    #[derive(Deserialize)]
+    struct NewSession {
+        sha256: String,
+        size: u64,
+    }
+    let new: NewSession = match serde_json::from_slice(body) {
+        Ok(new) => new,
+        Err(e) => return (400, format!("bad upload session json: {e}")),
+    };
+
+    let index = match state
+        .sessions
+        .iter()
+        .position(|s| s.sha256 == new.sha256 && s.size == new.size)
+    {
+        Some(index) => index,
+        None => {
+            state.sessions.push(UploadSession {
+                sha256: new.sha256,
+                size: new.size,
+                data: Vec::new(),
+                job: None,
+            });
+            state.sessions.len() - 1
+        }
+    };
+    let session = &state.sessions[index];
+    (
+        200,
+        json!({
+            "upload_id": format!("upload-{}", index + 1),
+            "offset": session.data.len(),
+            "id": session.job,
+        })
+        .to_string(),
+    )
+}
+
+/// Appends the chunk if it starts at the session offset, the last one creates the job.
+fn upload_chunk(state: &mut MockState, upload_id: &str, request: &Request) -> (u16, String) {
+    let index = upload_id
+        .strip_prefix("upload-")
+        .and_then(|i| i.parse::<usize>().ok())
+        .filter(|i| (1..=state.sessions.len()).contains(i));
+    let Some(index) = index.map(|i| i - 1) else {
+        return (404, format!("upload {upload_id} not found"));
+    };
+
+    let start = request
+        .headers
+        .get("content-range")
+        .and_then(|range| range.strip_prefix("bytes "))
+        .and_then(|range| range.split_once('-'))
+        .and_then(|(start, _)| start.parse::<usize>().ok());
+    let session = &mut state.sessions[index];
+    if start != Some(session.data.len()) {
+        return (409, json!({ "offset": session.data.len() }).to_string());
+    }
+
+    session.data.extend_from_slice(&request.body);
+    let offset = session.data.len();
+    if offset as u64 > session.size {
+        return (400, format!("upload {upload_id} is larger than its size"));
+    }
+    state.record.chunks += 1;
+
+    let complete = offset as u64 == session.size && session.job.is_none();
+    if complete {
+        let data = std::mem::take(&mut state.sessions[index].data);
+        let id = state.add_job(&data);
+        let session = &mut state.sessions[index];
+        session.data = data;
+        session.job = Some(id);
+    }
+    (
+        200,
+        json!({ "offset": offset, "id": state.sessions[index].job }).to_string(),
+    )
+}
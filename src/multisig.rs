@@ -0,0 +1,755 @@
+use crate::client::{Events, MubertClient};
+use crate::dispatch::TxOutcome;
+use crate::error::Error;
+use crate::signer::Signer;
+use crate::tx::{print_events, RawCall};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use subxt::dynamic::Value;
+use subxt::ext::codec::Encode;
+use subxt::ext::scale_decode::DecodeAsType;
+use subxt::ext::scale_value::Composite;
+use subxt::tx::Payload;
+use subxt::utils::{AccountId32, H256};
+use subxt::{OnlineClient, PolkadotConfig};
+
+// Multisig and Proxy are not part of the generated api, their calls are built dynamically
+// against the metadata of the node.
+
+#[derive(clap::Args, Clone, Debug, Default)]
+pub struct WrapArgs {
+    #[arg(
+        long,
+        value_name = "THRESHOLD",
+        requires = "signatories",
+        help = "send the call from the multisig account of --signatories, approvals are kept in --multisig-dir"
+    )]
+    pub as_multisig: Option<u16>,
+    #[arg(
+        long,
+        value_delimiter = ',',
+        requires = "as_multisig",
+        help = "multisig accounts, comma separated, with or without the signer"
+    )]
+    pub signatories: Vec<AccountId32>,
+    #[arg(
+        long,
+        value_name = "ACCOUNT",
+        help = "send the call on behalf of this account, the signer (or the multisig) is its proxy"
+    )]
+    pub via_proxy: Option<AccountId32>,
+    #[command(flatten)]
+    pub dir: MultisigDirArgs,
+}
+
+#[derive(clap::Args, Clone, Debug, Default)]
+pub struct MultisigDirArgs {
+    #[arg(
+        long,
+        env = "MUBERT_MULTISIG_DIR",
+        help = "pending multisig calls, <config dir>/mubert-cli/multisig by default"
+    )]
+    pub multisig_dir: Option<PathBuf>,
+}
+
+impl MultisigDirArgs {
+    pub fn dir(&self) -> Result<PathBuf, Error> {
+        Ok(match &self.multisig_dir {
+            Some(dir) => dir.clone(),
+            None => dirs::config_dir()
+                .ok_or_else(|| Error::Config("no config dir, set --multisig-dir".into()))?
+                .join("mubert-cli")
+                .join("multisig"),
+        })
+    }
+}
+
+impl WrapArgs {
+    /// Wraps the signer when the call goes through a multisig or a proxy.
+    pub fn wrap(&self, signer: Signer) -> Result<Signer, Error> {
+        if self.as_multisig.is_none() && self.via_proxy.is_none() {
+            return Ok(signer);
+        }
+        let multisig = match self.as_multisig {
+            Some(threshold) => Some(MultisigAccount::new(
+                threshold,
+                &self.signatories,
+                &signer.account_id(),
+            )?),
+            None => None,
+        };
+        let wrap = CallWrap {
+            multisig,
+            proxy: self.via_proxy.clone(),
+            dir: self.dir.dir()?,
+        };
+        Ok(Signer::Wrapped(Box::new(signer), wrap))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MultisigAccount {
+    pub threshold: u16,
+    /// All signatories, sorted.
+    pub signatories: Vec<AccountId32>,
+}
+
+impl MultisigAccount {
+    pub fn new(
+        threshold: u16,
+        signatories: &[AccountId32],
+        signer: &AccountId32,
+    ) -> Result<Self, Error> {
+        let mut signatories = signatories.to_vec();
+        signatories.push(signer.clone());
+        signatories.sort();
+        signatories.dedup();
+        if signatories.len() < 2 {
+            return Err(Error::Key(
+                "multisig needs at least one signatory besides the signer".into(),
+            ));
+        }
+        if threshold == 0 || threshold as usize > signatories.len() {
+            return Err(Error::Config(format!(
+                "multisig threshold must be from 1 to {}",
+                signatories.len()
+            )));
+        }
+        Ok(MultisigAccount {
+            threshold,
+            signatories,
+        })
+    }
+
+    /// Same derivation as pallet_multisig `multi_account_id`.
+    pub fn account_id(&self) -> AccountId32 {
+        let entropy = (b"modlpy/utilisuba", &self.signatories, self.threshold).encode();
+        AccountId32(sp_crypto_hashing::blake2_256(&entropy))
+    }
+
+    fn others(&self, signer: &AccountId32) -> Result<Vec<AccountId32>, Error> {
+        if !self.signatories.contains(signer) {
+            return Err(Error::Key(format!(
+                "{signer} is not a signatory of the multisig"
+            )));
+        }
+        Ok(self
+            .signatories
+            .iter()
+            .filter(|a| *a != signer)
+            .cloned()
+            .collect())
+    }
+}
+
+/// How the call of a wrapped signer is sent.
+#[derive(Clone)]
+pub struct CallWrap {
+    pub multisig: Option<MultisigAccount>,
+    pub proxy: Option<AccountId32>,
+    pub dir: PathBuf,
+}
+
+impl CallWrap {
+    pub async fn submit<Call: Payload>(
+        &self,
+        api: &OnlineClient<PolkadotConfig>,
+        signer: &Signer,
+        call: &Call,
+        timeout: Duration,
+    ) -> Result<TxOutcome<Events>, Error> {
+        let call_data = self.call_data(api, call)?;
+        match &self.multisig {
+            Some(multisig) => {
+                let pending = PendingMultisig::new(multisig.clone(), &call_data);
+                pending.submit(api, signer, &self.dir, timeout).await
+            }
+            None => signer.submit(api, &RawCall(call_data), timeout).await,
+        }
+    }
+
+    /// Call data sent by the signer, only the proxy part is applied here.
+    pub fn call_data<Call: Payload>(
+        &self,
+        api: &OnlineClient<PolkadotConfig>,
+        call: &Call,
+    ) -> Result<Vec<u8>, Error> {
+        let call_data = api
+            .tx()
+            .call_data(call)
+            .map_err(|e| Error::Decode(format!("can not encode call: {e}")))?;
+        let Some(real) = &self.proxy else {
+            return Ok(call_data);
+        };
+        let proxy = subxt::dynamic::tx(
+            "Proxy",
+            "proxy",
+            Composite::named([
+                ("real", multi_address(real)),
+                ("force_proxy_type", Value::unnamed_variant("None", [])),
+                ("call", call_value(api, &call_data)?),
+            ]),
+        );
+        api.tx().call_data(&proxy).map_err(|e| {
+            Error::Config(format!(
+                "proxy call, does the runtime have the Proxy pallet? {e}"
+            ))
+        })
+    }
+}
+
+/// Multisig call waiting for approvals, shared with the co-signers through the multisig dir.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct PendingMultisig {
+    pub multisig_account: AccountId32,
+    #[serde(flatten)]
+    pub multisig: MultisigAccount,
+    pub call_hash: H256,
+    pub call_data: String,
+}
+
+/// Returned as `TxOutcome::Pending` when the call still needs approvals.
+#[derive(Debug)]
+pub struct MultisigPending {
+    pub call_hash: H256,
+    pub approvals: usize,
+    pub threshold: u16,
+}
+
+impl fmt::Display for MultisigPending {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "multisig call {:?} has {}/{} approvals, co-signers run: mubert-cli multisig approve --call-hash={:?}",
+            self.call_hash, self.approvals, self.threshold, self.call_hash
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, DecodeAsType, Clone, Copy, Debug, PartialEq)]
+#[decode_as_type(crate_path = "subxt::ext::scale_decode")]
+pub struct Timepoint {
+    pub height: u32,
+    pub index: u32,
+}
+
+/// `pallet_multisig::Multisig` storage value.
+#[derive(DecodeAsType, Debug)]
+#[decode_as_type(crate_path = "subxt::ext::scale_decode")]
+pub struct MultisigInfo {
+    pub when: Timepoint,
+    pub deposit: u128,
+    pub depositor: AccountId32,
+    pub approvals: Vec<AccountId32>,
+}
+
+#[derive(DecodeAsType)]
+#[decode_as_type(crate_path = "subxt::ext::scale_decode")]
+struct Weight {
+    ref_time: u64,
+    proof_size: u64,
+}
+
+#[derive(DecodeAsType)]
+#[decode_as_type(crate_path = "subxt::ext::scale_decode")]
+struct CallInfo {
+    weight: Weight,
+}
+
+fn multi_address(account: &AccountId32) -> Value {
+    Value::unnamed_variant("Id", [Value::from_bytes(account.0)])
+}
+
+fn accounts(accounts: &[AccountId32]) -> Value {
+    Value::unnamed_composite(accounts.iter().map(|a| Value::from_bytes(a.0)))
+}
+
+fn timepoint(timepoint: Timepoint) -> Value {
+    Value::named_composite([
+        ("height", Value::u128(timepoint.height.into())),
+        ("index", Value::u128(timepoint.index.into())),
+    ])
+}
+
+fn maybe_timepoint(maybe_timepoint: Option<Timepoint>) -> Value {
+    match maybe_timepoint {
+        Some(t) => Value::unnamed_variant("Some", [timepoint(t)]),
+        None => Value::unnamed_variant("None", []),
+    }
+}
+
+fn weight(ref_time: u64, proof_size: u64) -> Value {
+    Value::named_composite([
+        ("ref_time", Value::u128(ref_time.into())),
+        ("proof_size", Value::u128(proof_size.into())),
+    ])
+}
+
+/// Runtime call as a dynamic value, so it can be nested in another call.
+fn call_value(api: &OnlineClient<PolkadotConfig>, call_data: &[u8]) -> Result<Value, Error> {
+    let metadata = api.metadata();
+    let value = subxt::ext::scale_value::scale::decode_as_type(
+        &mut &call_data[..],
+        metadata.outer_enums().call_enum_ty(),
+        metadata.types(),
+    )
+    .map_err(|e| Error::Decode(format!("can not decode call: {e}")))?;
+    Ok(value.remove_context())
+}
+
+/// Weight the final approval has to allow for executing the call.
+async fn call_weight(api: &OnlineClient<PolkadotConfig>, call_data: &[u8]) -> Result<Value, Error> {
+    let payload = subxt::dynamic::runtime_api_call(
+        "TransactionPaymentCallApi",
+        "query_call_info",
+        vec![
+            call_value(api, call_data)?,
+            Value::u128(call_data.len() as u128),
+        ],
+    );
+    let info: CallInfo = api
+        .runtime_api()
+        .at_latest()
+        .await?
+        .call(payload)
+        .await
+        .map_err(|e| Error::Rpc(format!("call weight: {e}")))?
+        .as_type()?;
+    Ok(weight(info.weight.ref_time, info.weight.proof_size))
+}
+
+async fn fetch_multisig(
+    api: &OnlineClient<PolkadotConfig>,
+    multisig_account: &AccountId32,
+    call_hash: H256,
+) -> Result<Option<MultisigInfo>, Error> {
+    let query = subxt::dynamic::storage(
+        "Multisig",
+        "Multisigs",
+        vec![
+            Value::from_bytes(multisig_account.0),
+            Value::from_bytes(call_hash.0),
+        ],
+    );
+    let info = api
+        .storage()
+        .at_latest()
+        .await?
+        .fetch(&query)
+        .await
+        .map_err(|e| {
+            Error::Rpc(format!(
+                "multisig storage, does the runtime have the Multisig pallet? {e}"
+            ))
+        })?;
+    match info {
+        Some(info) => Ok(Some(info.as_type()?)),
+        None => Ok(None),
+    }
+}
+
+impl PendingMultisig {
+    pub fn new(multisig: MultisigAccount, call_data: &[u8]) -> Self {
+        PendingMultisig {
+            multisig_account: multisig.account_id(),
+            multisig,
+            call_hash: H256(sp_crypto_hashing::blake2_256(call_data)),
+            call_data: format!("0x{}", hex::encode(call_data)),
+        }
+    }
+
+    fn path(dir: &Path, call_hash: &H256) -> PathBuf {
+        dir.join(format!("{call_hash:?}.json"))
+    }
+
+    pub fn load(dir: &Path, call_hash: &H256) -> Result<Self, Error> {
+        let path = Self::path(dir, call_hash);
+        let data = std::fs::read_to_string(&path).map_err(|e| {
+            Error::NotFound(format!(
+                "no pending multisig call {call_hash:?} in {}: {e}",
+                dir.display()
+            ))
+        })?;
+        serde_json::from_str(&data)
+            .map_err(|e| Error::Decode(format!("bad {}: {e}", path.display())))
+    }
+
+    fn save(&self, dir: &Path) -> Result<(), Error> {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| Error::Config(format!("can not create {}: {e}", dir.display())))?;
+        let path = Self::path(dir, &self.call_hash);
+        std::fs::write(&path, serde_json::to_string_pretty(self)?)
+            .map_err(|e| Error::Config(format!("can not write {}: {e}", path.display())))?;
+        Ok(())
+    }
+
+    /// Saves the call once its approval is sent or built for a later submit,
+    /// a dry run or a failed approval leaves no call in `dir`.
+    fn keep<T>(&self, dir: &Path, outcome: &TxOutcome<T>) -> Result<(), Error> {
+        match outcome {
+            TxOutcome::Submitted(_) | TxOutcome::Built(_) => self.save(dir),
+            TxOutcome::Pending(_) | TxOutcome::DryRun(_) => Ok(()),
+        }
+    }
+
+    fn remove(&self, dir: &Path) {
+        let _ = std::fs::remove_file(Self::path(dir, &self.call_hash));
+    }
+
+    fn call_data(&self) -> Result<Vec<u8>, Error> {
+        hex::decode(self.call_data.trim_start_matches("0x"))
+            .map_err(|e| Error::Decode(format!("bad call_data: {e}")))
+    }
+
+    /// Sends the next approval round: the first approvals only record the call hash,
+    /// the last one sends the call and executes it. The call is saved in `dir` once its
+    /// approval is sent and stays there until it is executed, a failed last approval
+    /// can be sent again.
+    pub async fn submit(
+        &self,
+        api: &OnlineClient<PolkadotConfig>,
+        signer: &Signer,
+        dir: &Path,
+        timeout: Duration,
+    ) -> Result<TxOutcome<Events>, Error> {
+        let account_id = signer.account_id();
+        let others = accounts(&self.multisig.others(&account_id)?);
+        let threshold = self.multisig.threshold;
+        let call_data = self.call_data()?;
+
+        if threshold == 1 {
+            let call = subxt::dynamic::tx(
+                "Multisig",
+                "as_multi_threshold_1",
+                Composite::named([
+                    ("other_signatories", others),
+                    ("call", call_value(api, &call_data)?),
+                ]),
+            );
+            return signer.submit(api, &call, timeout).await;
+        }
+
+        let info = fetch_multisig(api, &self.multisig_account, self.call_hash).await?;
+        let approvals = info.as_ref().map_or(0, |info| info.approvals.len());
+        if info
+            .as_ref()
+            .is_some_and(|info| info.approvals.contains(&account_id))
+        {
+            return Err(Error::Config(format!(
+                "{account_id} already approved, {approvals}/{threshold} approvals"
+            )));
+        }
+
+        let when = info.map(|info| info.when);
+        if approvals + 1 >= threshold as usize {
+            let call = subxt::dynamic::tx(
+                "Multisig",
+                "as_multi",
+                Composite::named([
+                    ("threshold", Value::u128(threshold.into())),
+                    ("other_signatories", others),
+                    ("maybe_timepoint", maybe_timepoint(when)),
+                    ("call", call_value(api, &call_data)?),
+                    ("max_weight", call_weight(api, &call_data).await?),
+                ]),
+            );
+            // a loaded call stays in `dir` until it is executed
+            let outcome = signer.submit(api, &call, timeout).await?;
+            match outcome {
+                TxOutcome::Submitted(_) => self.remove(dir),
+                _ => self.keep(dir, &outcome)?,
+            }
+            return Ok(outcome);
+        }
+
+        let call = subxt::dynamic::tx(
+            "Multisig",
+            "approve_as_multi",
+            Composite::named([
+                ("threshold", Value::u128(threshold.into())),
+                ("other_signatories", others),
+                ("maybe_timepoint", maybe_timepoint(when)),
+                ("call_hash", Value::from_bytes(self.call_hash.0)),
+                ("max_weight", weight(0, 0)),
+            ]),
+        );
+        let outcome = signer.submit(api, &call, timeout).await?;
+        self.keep(dir, &outcome)?;
+        if let Err(outcome) = outcome.into_submitted() {
+            return Ok(outcome);
+        }
+
+        Ok(TxOutcome::Pending(MultisigPending {
+            call_hash: self.call_hash,
+            approvals: approvals + 1,
+            threshold,
+        }))
+    }
+}
+
+#[derive(Serialize)]
+struct PendingInfo {
+    #[serde(flatten)]
+    pending: PendingMultisig,
+    approvals: Vec<AccountId32>,
+    when: Option<Timepoint>,
+}
+
+/// The approvals are signed by the signatory itself, the call is already wrapped.
+fn check_plain(signer: &Signer) -> Result<(), Error> {
+    match signer {
+        Signer::Wrapped(..) => Err(Error::Config(
+            "--as-multisig and --via-proxy are not used here".into(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+pub async fn multisig_list(client: &MubertClient, dir: &MultisigDirArgs) -> Result<(), Error> {
+    let dir = dir.dir()?;
+    let api = client.api();
+
+    let mut list = Vec::new();
+    if dir.exists() {
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let data = std::fs::read_to_string(&path)?;
+            let pending: PendingMultisig = serde_json::from_str(&data)
+                .map_err(|e| Error::Decode(format!("bad {}: {e}", path.display())))?;
+            let info = fetch_multisig(api, &pending.multisig_account, pending.call_hash).await?;
+            list.push(PendingInfo {
+                approvals: info
+                    .as_ref()
+                    .map(|i| i.approvals.clone())
+                    .unwrap_or_default(),
+                when: info.map(|i| i.when),
+                pending,
+            });
+        }
+    }
+    list.sort_by_key(|p| p.when.map(|w| (w.height, w.index)));
+
+    println!("{}", serde_json::to_string_pretty(&list)?);
+    Ok(())
+}
+
+pub async fn multisig_approve(
+    client: &MubertClient,
+    dir: &MultisigDirArgs,
+    call_hash: &H256,
+) -> Result<(), Error> {
+    let dir = dir.dir()?;
+    let pending = PendingMultisig::load(&dir, call_hash)?;
+    let signer = client.signer()?;
+    check_plain(signer)?;
+
+    println!("Submitting multisig approval...");
+    let outcome = pending
+        .submit(client.api(), signer, &dir, client.tx_timeout())
+        .await
+        .inspect_err(|_| {
+            eprintln!(
+                "The pending call is kept in {}, approve again to retry",
+                PendingMultisig::path(&dir, call_hash).display()
+            )
+        })?;
+    let Some(events) = outcome.print_unsent()? else {
+        return Ok(());
+    };
+    print_events(&events)
+}
+
+pub async fn multisig_cancel(
+    client: &MubertClient,
+    dir: &MultisigDirArgs,
+    call_hash: &H256,
+) -> Result<(), Error> {
+    let dir = dir.dir()?;
+    let pending = PendingMultisig::load(&dir, call_hash)?;
+    let signer = client.signer()?;
+    check_plain(signer)?;
+
+    let info = fetch_multisig(client.api(), &pending.multisig_account, pending.call_hash)
+        .await?
+        .ok_or_else(|| Error::NotFound("multisig call is not on chain".into()))?;
+    let others = pending.multisig.others(&signer.account_id())?;
+    let call = subxt::dynamic::tx(
+        "Multisig",
+        "cancel_as_multi",
+        Composite::named([
+            ("threshold", Value::u128(pending.multisig.threshold.into())),
+            ("other_signatories", accounts(&others)),
+            ("timepoint", timepoint(info.when)),
+            ("call_hash", Value::from_bytes(pending.call_hash.0)),
+        ]),
+    );
+
+    println!("Submitting multisig cancel...");
+    let Some(events) = client.submit(&call).await?.print_unsent()? else {
+        return Ok(());
+    };
+    print_events(&events)?;
+    pending.remove(&dir);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dry_run::DryRun;
+    use crate::signer::Scheme;
+    use std::str::FromStr;
+    use subxt_signer::SecretUri;
+
+    fn dev(name: &str) -> Signer {
+        let uri = SecretUri::from_str(&format!("//{name}")).unwrap();
+        Signer::from_uri(&uri, Scheme::Sr25519).unwrap()
+    }
+
+    #[test]
+    fn test_multisig_account() {
+        let (alice, bob, charlie) = (
+            dev("Alice").account_id(),
+            dev("Bob").account_id(),
+            dev("Charlie").account_id(),
+        );
+
+        let multisig = MultisigAccount::new(2, &[charlie.clone(), bob.clone()], &alice).unwrap();
+        let same =
+            MultisigAccount::new(2, &[alice.clone(), bob.clone(), charlie.clone()], &bob).unwrap();
+        assert_eq!(multisig, same);
+        assert_eq!(multisig.account_id(), same.account_id());
+        let mut others = vec![alice.clone(), charlie.clone()];
+        others.sort();
+        assert_eq!(multisig.others(&bob).unwrap(), others);
+        assert_ne!(
+            multisig.account_id(),
+            MultisigAccount::new(3, &[charlie.clone(), bob.clone()], &alice)
+                .unwrap()
+                .account_id()
+        );
+
+        assert!(MultisigAccount::new(4, &[charlie.clone(), bob.clone()], &alice).is_err());
+        assert!(MultisigAccount::new(1, &[], &alice).is_err());
+        assert!(multisig.others(&dev("Dave").account_id()).is_err());
+    }
+
+    #[test]
+    fn test_pending_state() {
+        let dir = std::env::temp_dir().join(format!("mubert-multisig-{}", std::process::id()));
+        let multisig =
+            MultisigAccount::new(2, &[dev("Bob").account_id()], &dev("Alice").account_id())
+                .unwrap();
+        let pending = PendingMultisig::new(multisig, &[1, 2, 3]);
+        pending.save(&dir).unwrap();
+
+        let loaded = PendingMultisig::load(&dir, &pending.call_hash).unwrap();
+        assert_eq!(loaded, pending);
+        assert_eq!(loaded.call_data().unwrap(), vec![1, 2, 3]);
+
+        pending.remove(&dir);
+        assert!(PendingMultisig::load(&dir, &pending.call_hash).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_pending_kept_once_sent() {
+        let dir = std::env::temp_dir().join(format!("mubert-multisig-keep-{}", std::process::id()));
+        let multisig =
+            MultisigAccount::new(2, &[dev("Bob").account_id()], &dev("Alice").account_id())
+                .unwrap();
+        let pending = PendingMultisig::new(multisig, &[1, 2, 3]);
+
+        let dry_run = TxOutcome::<()>::DryRun(DryRun {
+            result: Ok(()),
+            partial_fee: 0,
+            ref_time: 0,
+            proof_size: 0,
+        });
+        pending.keep(&dir, &dry_run).unwrap();
+        assert!(!dir.exists());
+
+        pending
+            .keep(&dir, &TxOutcome::<()>::Built("tx.json".into()))
+            .unwrap();
+        assert_eq!(
+            PendingMultisig::load(&dir, &pending.call_hash).unwrap(),
+            pending
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Needs a dev node with the Multisig pallet, like `mubert-node --dev`:
+    /// `MUBERT_TEST_NODE_URL=ws://127.0.0.1:9944 cargo test -- --ignored`
+    #[tokio::test]
+    #[ignore]
+    async fn test_dev_chain_multisig() {
+        let node_url = std::env::var("MUBERT_TEST_NODE_URL")
+            .unwrap_or_else(|_| "ws://127.0.0.1:9944".to_string());
+        let dir = std::env::temp_dir().join(format!("mubert-multisig-dev-{}", std::process::id()));
+        let dir_args = MultisigDirArgs {
+            multisig_dir: Some(dir.clone()),
+        };
+        let wrap = WrapArgs {
+            as_multisig: Some(2),
+            signatories: vec![dev("Bob").account_id(), dev("Charlie").account_id()],
+            via_proxy: None,
+            dir: dir_args.clone(),
+        };
+
+        // the multisig account pays the deposit of the created author
+        let multisig = MultisigAccount::new(2, &wrap.signatories, &dev("Alice").account_id())
+            .unwrap()
+            .account_id();
+        let api = OnlineClient::<PolkadotConfig>::from_url(&node_url)
+            .await
+            .unwrap();
+        let transfer = subxt::dynamic::tx(
+            "Balances",
+            "transfer_keep_alive",
+            Composite::named([
+                ("dest", multi_address(&multisig)),
+                ("value", Value::u128(1_000_000_000_000_000)),
+            ]),
+        );
+        dev("Alice")
+            .submit(&api, &transfer, Duration::from_secs(60))
+            .await
+            .unwrap()
+            .into_result()
+            .unwrap();
+
+        let client = |signer| {
+            MubertClient::builder()
+                .node_url(&node_url)
+                .signer(signer)
+                .build()
+        };
+        let nickname = format!("multisig-{}", std::process::id());
+        let dry_run = client(wrap.wrap(Signer::DryRun(Box::new(dev("Alice")))).unwrap())
+            .await
+            .unwrap();
+        let outcome = dry_run.create_author(&nickname, None, None).await.unwrap();
+        assert!(matches!(outcome, TxOutcome::DryRun(_)), "{outcome}");
+        assert!(!dir.exists());
+
+        let alice = client(wrap.wrap(dev("Alice")).unwrap()).await.unwrap();
+        let outcome = alice.create_author(&nickname, None, None).await.unwrap();
+        let TxOutcome::Pending(pending) = outcome else {
+            panic!("not pending: {outcome}");
+        };
+        assert_eq!(pending.approvals, 1);
+
+        let bob = client(dev("Bob")).await.unwrap();
+        multisig_approve(&bob, &dir_args, &pending.call_hash)
+            .await
+            .unwrap();
+        assert!(PendingMultisig::load(&dir, &pending.call_hash).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
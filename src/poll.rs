@@ -0,0 +1,209 @@
+use crate::error::Error;
+use rand::Rng;
+use std::fmt;
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Job the cli was waiting for, it keeps running off chain or on chain after the wait stops.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Waiting {
+    FingerprintJob(String),
+    ArweaveTask(u64),
+}
+
+impl fmt::Display for Waiting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Waiting::FingerprintJob(id) => write!(f, "fingerprint job {id}"),
+            Waiting::ArweaveTask(id) => write!(f, "arweave task {id}"),
+        }
+    }
+}
+
+/// State of the polled job.
+pub enum Poll<T> {
+    Ready(T),
+    /// Not done yet, with its status.
+    Pending(String),
+}
+
+/// How a job is polled: the interval doubles up to `max_interval`, with a random jitter,
+/// until `timeout` is passed.
+#[derive(Clone, Debug)]
+pub struct PollOptions {
+    pub interval: Duration,
+    pub max_interval: Duration,
+    pub timeout: Duration,
+    /// Stops with `Error::Cancelled` on Ctrl-C, the cli sets it.
+    pub ctrl_c: bool,
+}
+
+impl PollOptions {
+    /// Polls from `interval`, up to a minute between two polls and for 30 min at most.
+    pub fn every(interval: Duration) -> Self {
+        PollOptions {
+            interval,
+            max_interval: Duration::from_secs(60).max(interval),
+            timeout: Duration::from_secs(30 * 60),
+            ctrl_c: false,
+        }
+    }
+}
+
+#[derive(clap::Args, Clone, Debug, Default)]
+pub struct PollArgs {
+    #[arg(
+        long,
+        help = "first interval in seconds between two fingerprint or arweave status checks, doubled up to 60s"
+    )]
+    pub poll_interval: Option<u64>,
+    #[arg(
+        long,
+        help = "max seconds to wait for the fingerprint or the arweave task, 1800 by default"
+    )]
+    pub timeout: Option<u64>,
+}
+
+impl PollArgs {
+    /// Options of the cli, stopped by Ctrl-C, from the defaults of the wait.
+    pub fn options(&self, default: PollOptions) -> PollOptions {
+        let interval = self
+            .poll_interval
+            .map(|s| Duration::from_secs(s.max(1)))
+            .unwrap_or(default.interval);
+        PollOptions {
+            interval,
+            max_interval: default.max_interval.max(interval),
+            timeout: self
+                .timeout
+                .map(Duration::from_secs)
+                .unwrap_or(default.timeout),
+            ctrl_c: true,
+        }
+    }
+}
+
+/// Between half and the whole interval, so concurrent waits do not poll together.
+fn jitter(interval: Duration) -> Duration {
+    interval.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+}
+
+/// Calls `check` until the job is ready. Transient errors, like a dropped connection
+/// or a 5xx status, are polled again, the other ones are returned.
+pub async fn poll_until<T, F, Fut>(
+    options: &PollOptions,
+    waiting: Waiting,
+    mut check: F,
+) -> Result<T, Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<Poll<T>, Error>>,
+{
+    let deadline = Instant::now() + options.timeout;
+    let mut interval = options.interval;
+    loop {
+        let status = match check().await {
+            Ok(Poll::Ready(ready)) => return Ok(ready),
+            Ok(Poll::Pending(status)) => status,
+            Err(e) if e.is_transient() => e.to_string(),
+            Err(e) => return Err(e),
+        };
+
+        let now = Instant::now();
+        if now >= deadline {
+            return Err(Error::Timeout(format!(
+                "{waiting} not done in {:?}, last status: {status}",
+                options.timeout
+            )));
+        }
+
+        let delay = jitter(interval).min(deadline - now);
+        if options.ctrl_c {
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = tokio::signal::ctrl_c() => return Err(Error::Cancelled(waiting)),
+            }
+        } else {
+            tokio::time::sleep(delay).await;
+        }
+        interval = (interval * 2).min(options.max_interval);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn options(timeout: Duration) -> PollOptions {
+        PollOptions {
+            timeout,
+            ..PollOptions::every(Duration::from_millis(1))
+        }
+    }
+
+    #[test]
+    fn test_jitter() {
+        for _ in 0..100 {
+            let delay = jitter(Duration::from_secs(10));
+            assert!(delay >= Duration::from_secs(5) && delay <= Duration::from_secs(10));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_poll_until() {
+        let polls = Cell::new(0);
+        let ready = poll_until(
+            &options(Duration::from_secs(10)),
+            Waiting::ArweaveTask(1),
+            || async {
+                polls.set(polls.get() + 1);
+                match polls.get() {
+                    1 => Err(Error::Http("connection reset".into())),
+                    2 => Ok(Poll::Pending("Processing".into())),
+                    _ => Ok(Poll::Ready("url")),
+                }
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(ready, "url");
+        assert_eq!(polls.get(), 3);
+
+        let result = poll_until(
+            &options(Duration::from_secs(10)),
+            Waiting::ArweaveTask(1),
+            || async { Err::<Poll<()>, _>(Error::NotFound("task".into())) },
+        )
+        .await;
+        assert!(matches!(result, Err(Error::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_poll_timeout() {
+        let result = poll_until(
+            &options(Duration::from_millis(20)),
+            Waiting::FingerprintJob("job-1".into()),
+            || async { Ok(Poll::<()>::Pending("processing".into())) },
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(Error::Timeout(ref e)) if e == "fingerprint job job-1 not done in 20ms, last status: processing"
+        ));
+    }
+
+    #[test]
+    fn test_poll_args() {
+        let args = PollArgs {
+            poll_interval: Some(120),
+            timeout: None,
+        };
+        let options = args.options(PollOptions::every(Duration::from_secs(6)));
+        assert_eq!(options.interval, Duration::from_secs(120));
+        assert_eq!(options.max_interval, Duration::from_secs(120));
+        assert_eq!(options.timeout, Duration::from_secs(1800));
+        assert!(options.ctrl_c);
+    }
+}
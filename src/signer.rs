@@ -0,0 +1,534 @@
+use crate::client::{wait_events, Events};
+use crate::dispatch::TxOutcome;
+use crate::dry_run::dry_run;
+use crate::error::Error;
+use crate::keystore::KeystoreArgs;
+use crate::multisig::{CallWrap, WrapArgs};
+use crate::tx::{OfflineSigner, RawCall};
+use crate::SecretKeyFile;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::time::Duration;
+use subxt::config::{Config, ExtrinsicParams};
+use subxt::ext::codec::Encode;
+use subxt::tx::{Payload, SubmittableTransaction, TxProgress};
+use subxt::utils::{AccountId32, MultiSignature};
+use subxt::{OnlineClient, PolkadotConfig};
+use subxt_signer::bip39::Mnemonic;
+use subxt_signer::{ecdsa, sr25519, ExposeSecret, SecretUri};
+
+pub type Tx = SubmittableTransaction<PolkadotConfig, OnlineClient<PolkadotConfig>>;
+pub type Progress = TxProgress<PolkadotConfig, OnlineClient<PolkadotConfig>>;
+pub type TxParams =
+    <<PolkadotConfig as Config>::ExtrinsicParams as ExtrinsicParams<PolkadotConfig>>::Params;
+
+#[derive(clap::ValueEnum, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Scheme {
+    #[default]
+    Sr25519,
+    Ed25519,
+    Ecdsa,
+}
+
+// Where the transactions signing key comes from. There is no default signer,
+// dev accounts have to be requested explicitly with `--suri=//Alice`.
+#[derive(clap::Args, Clone, Debug, Default)]
+pub struct SignerArgs {
+    #[arg(
+        long,
+        conflicts_with_all = ["secret_key_file", "external_signer"],
+        help = "name of the keystore key, see the key command"
+    )]
+    pub key: Option<String>,
+    #[command(flatten)]
+    pub keystore: KeystoreArgs,
+    #[arg(
+        short = 's',
+        long,
+        conflicts_with = "external_signer",
+        help = "json file with the secretPhrase"
+    )]
+    pub secret_key_file: Option<PathBuf>,
+    #[arg(
+        long,
+        env = "MUBERT_SURI",
+        hide_env_values = true,
+        help = "secret uri: mnemonic, 0x hex seed or dev account like //Alice, with optional //hard/soft derivation and ///password"
+    )]
+    pub suri: Option<String>,
+    #[arg(long, help = "derivation path added to the secret, like //hard/soft")]
+    pub derivation: Option<String>,
+    #[arg(long, env = "MUBERT_PASSWORD", hide_env_values = true)]
+    pub password: Option<String>,
+    #[arg(long, value_enum, default_value_t)]
+    pub scheme: Scheme,
+    #[arg(
+        long,
+        help = "command that signs the payloads, talks json lines over stdin/stdout"
+    )]
+    pub external_signer: Option<String>,
+    #[command(flatten)]
+    pub wrap: WrapArgs,
+}
+
+pub enum Signer {
+    Sr25519(sr25519::Keypair),
+    Ed25519(ed25519_zebra::SigningKey),
+    Ecdsa(ecdsa::Keypair),
+    External(ExternalSigner),
+    /// Only writes the unsigned transaction, see `tx build`.
+    Offline(OfflineSigner),
+    /// Sends the calls through a multisig or a proxy.
+    Wrapped(Box<Signer>, CallWrap),
+    /// Signs and only dry runs the transaction, see `--dry-run`.
+    DryRun(Box<Signer>),
+}
+
+/// Signer running in a subprocess, like a hardware wallet bridge.
+///
+/// Every request runs the command once, writes one json line to its stdin and reads
+/// one json line from its stdout:
+/// `{"type":"account","scheme":"sr25519"}` -> `{"account_id":"5..."}`,
+/// `{"type":"sign","scheme":"sr25519","payload":"0x.."}` -> `{"signature":"0x.."}`.
+pub struct ExternalSigner {
+    command: String,
+    scheme: Scheme,
+    account_id: AccountId32,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum ExternalRequest<'a> {
+    Account { scheme: Scheme },
+    Sign { scheme: Scheme, payload: &'a str },
+}
+
+#[derive(Deserialize)]
+struct AccountResponse {
+    account_id: AccountId32,
+}
+
+#[derive(Deserialize)]
+struct SignResponse {
+    signature: String,
+}
+
+impl ExternalSigner {
+    pub fn new(command: &str, scheme: Scheme) -> Result<Self, Error> {
+        let response: AccountResponse =
+            run_external(command, &ExternalRequest::Account { scheme })?;
+        Ok(ExternalSigner {
+            command: command.to_string(),
+            scheme,
+            account_id: response.account_id,
+        })
+    }
+
+    fn sign(&self, payload: &[u8]) -> Result<MultiSignature, Error> {
+        let payload = format!("0x{}", hex::encode(payload));
+        let response: SignResponse = run_external(
+            &self.command,
+            &ExternalRequest::Sign {
+                scheme: self.scheme,
+                payload: &payload,
+            },
+        )?;
+        let signature = response.signature.trim_start_matches("0x");
+        let signature = hex::decode(signature)
+            .map_err(|e| Error::Decode(format!("external signer signature: {e}")))?;
+
+        let bad_len = |_| {
+            Error::Decode(format!(
+                "external signer signature: bad length {}",
+                signature.len()
+            ))
+        };
+        let signature = match self.scheme {
+            Scheme::Sr25519 => {
+                MultiSignature::Sr25519(signature.clone().try_into().map_err(bad_len)?)
+            }
+            Scheme::Ed25519 => {
+                MultiSignature::Ed25519(signature.clone().try_into().map_err(bad_len)?)
+            }
+            Scheme::Ecdsa => MultiSignature::Ecdsa(signature.clone().try_into().map_err(bad_len)?),
+        };
+        Ok(signature)
+    }
+}
+
+fn run_external<T: for<'de> Deserialize<'de>>(
+    command: &str,
+    request: &ExternalRequest,
+) -> Result<T, Error> {
+    // stderr is inherited, the signer may ask the user to confirm on the device
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| Error::Key(format!("run external signer {command:?}: {e}")))?;
+
+    let mut stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| Error::Key("external signer stdin".into()))?;
+    writeln!(stdin, "{}", serde_json::to_string(request)?)?;
+    drop(stdin);
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(Error::Key(format!(
+            "external signer failed: {}",
+            output.status
+        )));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let line = stdout.lines().next().unwrap_or_default();
+    serde_json::from_str(line)
+        .map_err(|e| Error::Decode(format!("parsing external signer json: {e}")))
+}
+
+impl SignerArgs {
+    pub fn signer(&self) -> Result<Signer, Error> {
+        self.wrap.wrap(self.key_signer()?)
+    }
+
+    /// Signer of `tx build`, wrapped like `signer()` for `--as-multisig` and `--via-proxy`.
+    pub fn offline_signer(&self, offline: &OfflineSigner) -> Result<Signer, Error> {
+        self.wrap.wrap(Signer::Offline(offline.clone()))
+    }
+
+    /// Signer of `--dry-run`, the wrapping multisig or proxy call is the one dry run.
+    pub fn dry_run_signer(&self) -> Result<Signer, Error> {
+        self.wrap.wrap(Signer::DryRun(Box::new(self.key_signer()?)))
+    }
+
+    fn key_signer(&self) -> Result<Signer, Error> {
+        if let Some(command) = &self.external_signer {
+            return Ok(Signer::External(ExternalSigner::new(command, self.scheme)?));
+        }
+        if let Some(name) = &self.key {
+            let key = self.keystore.keystore()?.get(name)?;
+            return key.decrypt(&self.keystore.password(false)?);
+        }
+
+        let phrase = match (&self.secret_key_file, &self.suri) {
+            (Some(secret_key_file), _) => {
+                let secret_key_data = std::fs::read_to_string(secret_key_file).map_err(|e| {
+                    Error::Key(format!("read secret_key_file {:?}: {e}", secret_key_file))
+                })?;
+                let secret_key: SecretKeyFile = serde_json::from_str(secret_key_data.as_str())
+                    .map_err(|e| Error::Decode(format!("parsing json: {e}")))?;
+                secret_key.secret_phrase
+            }
+            (None, Some(suri)) => suri.clone(),
+            (None, None) => {
+                let help =
+                    "use --key, --secret-key-file, --suri (or MUBERT_SURI) or --external-signer, \
+                    dev accounts need an explicit --suri=//Alice";
+                return Err(Error::Key(format!("no signer given: {help}")));
+            }
+        };
+
+        let mut uri = SecretUri::from_str(&format!(
+            "{phrase}{}",
+            self.derivation.as_deref().unwrap_or_default()
+        ))
+        .map_err(|e| Error::Key(format!("bad secret uri: {e}")))?;
+        if let Some(password) = &self.password {
+            uri.password = Some(password.clone().into());
+        }
+
+        Signer::from_uri(&uri, self.scheme)
+    }
+}
+
+impl Signer {
+    pub fn from_uri(uri: &SecretUri, scheme: Scheme) -> Result<Self, Error> {
+        let signer = match scheme {
+            Scheme::Sr25519 => Signer::Sr25519(
+                sr25519::Keypair::from_uri(uri)
+                    .map_err(|e| Error::Key(format!("sr25519 key: {e}")))?,
+            ),
+            Scheme::Ecdsa => Signer::Ecdsa(
+                ecdsa::Keypair::from_uri(uri).map_err(|e| Error::Key(format!("ecdsa key: {e}")))?,
+            ),
+            Scheme::Ed25519 => Signer::Ed25519(ed25519_from_uri(uri)?),
+        };
+        Ok(signer)
+    }
+
+    pub fn account_id(&self) -> AccountId32 {
+        match self {
+            Signer::Sr25519(keypair) => keypair.public_key().to_account_id(),
+            Signer::Ecdsa(keypair) => keypair.public_key().to_account_id(),
+            Signer::Ed25519(key) => AccountId32(ed25519_zebra::VerificationKey::from(key).into()),
+            Signer::External(external) => external.account_id.clone(),
+            Signer::Offline(offline) => offline.account_id.clone(),
+            Signer::Wrapped(signer, _) | Signer::DryRun(signer) => signer.account_id(),
+        }
+    }
+
+    /// Whether the txs are sent to the chain, not only built by `tx build` or dry run.
+    pub fn sends_tx(&self) -> bool {
+        match self {
+            Signer::Offline(_) | Signer::DryRun(_) => false,
+            Signer::Wrapped(signer, _) => signer.sends_tx(),
+            _ => true,
+        }
+    }
+
+    pub fn sign(&self, payload: &[u8]) -> Result<MultiSignature, Error> {
+        let signature = match self {
+            Signer::Sr25519(keypair) => MultiSignature::Sr25519(keypair.sign(payload).0),
+            Signer::Ecdsa(keypair) => MultiSignature::Ecdsa(keypair.sign(payload).0),
+            Signer::Ed25519(key) => MultiSignature::Ed25519(key.sign(payload).into()),
+            Signer::External(external) => external.sign(payload)?,
+            Signer::Offline(_) => {
+                return Err(Error::Key(
+                    "offline signer can not sign, use tx sign".into(),
+                ))
+            }
+            Signer::Wrapped(signer, _) | Signer::DryRun(signer) => signer.sign(payload)?,
+        };
+        Ok(signature)
+    }
+
+    /// Signs the call with the given params, the account nonce is fetched when not set.
+    pub async fn create_signed<Call: Payload>(
+        &self,
+        api: &OnlineClient<PolkadotConfig>,
+        call: &Call,
+        params: TxParams,
+    ) -> Result<TxOutcome<Tx>, Error> {
+        if let Signer::Offline(offline) = self {
+            return Ok(TxOutcome::Built(offline.write(api, call).await?));
+        }
+        if let Signer::Wrapped(signer, wrap) = self {
+            if wrap.multisig.is_some() {
+                return Err(Error::Config(
+                    "multisig calls need approval rounds, they can not be sent here".into(),
+                ));
+            }
+            let call = RawCall(wrap.call_data(api, call)?);
+            return Box::pin(signer.create_signed(api, &call, params)).await;
+        }
+        if let Signer::DryRun(signer) = self {
+            return match Box::pin(signer.create_signed(api, call, params))
+                .await?
+                .into_submitted()
+            {
+                Ok(tx) => Ok(TxOutcome::DryRun(dry_run(api, &tx).await?)),
+                Err(outcome) => Ok(outcome),
+            };
+        }
+        let account_id = self.account_id();
+        let mut partial = api
+            .tx()
+            .create_partial(call, &account_id, params)
+            .await
+            .map_err(|e| Error::Rpc(format!("can not create tx: {e}")))?;
+        let signature = self.sign(&partial.signer_payload())?;
+        Ok(TxOutcome::Submitted(
+            partial.sign_with_account_and_signature(&account_id, &signature),
+        ))
+    }
+
+    /// Signs and submits the call, then waits up to `timeout` for its finalized events.
+    pub async fn submit<Call: Payload>(
+        &self,
+        api: &OnlineClient<PolkadotConfig>,
+        call: &Call,
+        timeout: Duration,
+    ) -> Result<TxOutcome<Events>, Error> {
+        if let Signer::Wrapped(signer, wrap) = self {
+            return Box::pin(wrap.submit(api, signer, call, timeout)).await;
+        }
+        let tx = match self
+            .create_signed(api, call, Default::default())
+            .await?
+            .into_submitted()
+        {
+            Ok(tx) => tx,
+            Err(outcome) => return Ok(outcome),
+        };
+        let progress = tx
+            .submit_and_watch()
+            .await
+            .map_err(|e| Error::Rpc(format!("can not submit tx: {e}")))?;
+        Ok(TxOutcome::Submitted(wait_events(progress, timeout).await?))
+    }
+}
+
+/// Root seed of the uri before derivation, from the hex seed or the mnemonic and password.
+pub(crate) fn uri_seed(uri: &SecretUri) -> Result<[u8; 32], Error> {
+    let phrase = uri.phrase.expose_secret();
+    let seed = match phrase.strip_prefix("0x") {
+        Some(hex_seed) => hex::decode(hex_seed)
+            .map_err(|e| Error::Key(format!("bad hex seed: {e}")))?
+            .try_into()
+            .map_err(|_| Error::Key("bad hex seed: must be 32 bytes".into()))?,
+        None => {
+            let mnemonic =
+                Mnemonic::parse(phrase).map_err(|e| Error::Key(format!("bad mnemonic: {e}")))?;
+            let password = uri.password.as_ref().map(|p| p.expose_secret());
+            seed_from_entropy(&mnemonic.to_entropy(), password.unwrap_or_default())
+        }
+    };
+    Ok(seed)
+}
+
+/// Substrate compatible ed25519 key, only hard derivation exists for this scheme.
+pub(crate) fn ed25519_from_uri(uri: &SecretUri) -> Result<ed25519_zebra::SigningKey, Error> {
+    let mut seed = uri_seed(uri)?;
+    for junction in uri.junctions.iter() {
+        if junction.is_soft() {
+            return Err(Error::Key(
+                "ed25519 key: soft derivation is not supported".into(),
+            ));
+        }
+        seed = ("Ed25519HDKD", seed, junction.inner()).using_encoded(sp_crypto_hashing::blake2_256);
+    }
+    Ok(ed25519_zebra::SigningKey::from(seed))
+}
+
+fn seed_from_entropy(entropy: &[u8], password: &str) -> [u8; 32] {
+    let mut seed = [0u8; 64];
+    pbkdf2::pbkdf2_hmac::<sha2::Sha512>(
+        entropy,
+        format!("mnemonic{password}").as_bytes(),
+        2048,
+        &mut seed,
+    );
+    seed[..32].try_into().expect("64 bytes seed")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn suri(suri: &str, scheme: Scheme) -> SignerArgs {
+        SignerArgs {
+            suri: Some(suri.to_string()),
+            scheme,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_no_signer() {
+        assert!(SignerArgs::default().signer().is_err());
+    }
+
+    #[test]
+    fn test_wrapped_offline_signer() {
+        let alice = sr25519::dev::alice().public_key().to_account_id();
+        let offline = OfflineSigner {
+            account_id: alice.clone(),
+            output: "tx.json".into(),
+            nonce: None,
+            era_period: 0,
+        };
+        let bob = sr25519::dev::bob().public_key().to_account_id();
+        let mut args = SignerArgs::default();
+        args.wrap.via_proxy = Some(bob.clone());
+
+        let signer = args.offline_signer(&offline).unwrap();
+        assert_eq!(signer.account_id(), alice);
+        let Signer::Wrapped(inner, wrap) = signer else {
+            panic!("not wrapped");
+        };
+        assert!(matches!(*inner, Signer::Offline(_)));
+        assert_eq!(wrap.proxy, Some(bob));
+        assert!(!Signer::Wrapped(inner, wrap).sends_tx());
+
+        let signer = SignerArgs::default().offline_signer(&offline).unwrap();
+        assert!(matches!(signer, Signer::Offline(_)));
+    }
+
+    #[test]
+    fn test_dev_uri() {
+        let signer = suri("//Alice", Scheme::Sr25519).signer().unwrap();
+        assert_eq!(
+            signer.account_id(),
+            sr25519::dev::alice().public_key().to_account_id()
+        );
+
+        let signer = suri("//Alice", Scheme::Ed25519).signer().unwrap();
+        assert_eq!(
+            hex::encode(signer.account_id().0),
+            "88dc3417d5058ec4b4503e0c12ea1a0a89be200fe98922423d4334014fa6b0ee"
+        );
+
+        let signer = suri("//Alice", Scheme::Ecdsa).signer().unwrap();
+        assert_eq!(
+            signer.account_id(),
+            ecdsa::dev::alice().public_key().to_account_id()
+        );
+    }
+
+    #[test]
+    fn test_derivation_and_password() {
+        let args = SignerArgs {
+            suri: Some(subxt_signer::DEV_PHRASE.to_string()),
+            derivation: Some("//Alice".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            args.signer().unwrap().account_id(),
+            sr25519::dev::alice().public_key().to_account_id()
+        );
+
+        let with_password = SignerArgs {
+            password: Some("secret".to_string()),
+            ..args.clone()
+        };
+        assert_ne!(
+            with_password.signer().unwrap().account_id(),
+            args.signer().unwrap().account_id()
+        );
+
+        assert!(suri("//Alice/soft", Scheme::Ed25519).signer().is_err());
+    }
+
+    #[test]
+    fn test_hex_seed() {
+        let seed = format!("0x{}", "01".repeat(32));
+        for scheme in [Scheme::Sr25519, Scheme::Ed25519, Scheme::Ecdsa] {
+            let signer = suri(&seed, scheme).signer().unwrap();
+            assert!(signer.sign(b"payload").is_ok());
+        }
+        assert!(suri("0x01", Scheme::Ed25519).signer().is_err());
+    }
+
+    #[test]
+    fn test_external_signer() {
+        let alice = sr25519::dev::alice().public_key().to_account_id();
+        let signature = format!("0x{}", "02".repeat(64));
+        let command = format!(
+            r#"read req; case "$req" in *account*) echo '{{"account_id":"{alice}"}}';; *) echo '{{"signature":"{signature}"}}';; esac"#
+        );
+        let args = SignerArgs {
+            external_signer: Some(command),
+            ..Default::default()
+        };
+
+        let signer = args.signer().unwrap();
+        assert_eq!(signer.account_id(), alice);
+        assert_eq!(
+            signer.sign(b"payload").unwrap(),
+            MultiSignature::Sr25519([2; 64])
+        );
+
+        let failing = SignerArgs {
+            external_signer: Some("exit 1".to_string()),
+            ..Default::default()
+        };
+        assert!(failing.signer().is_err());
+    }
+}
@@ -0,0 +1,383 @@
+use crate::client::{Events, MubertClient};
+use crate::dispatch::TxOutcome;
+use crate::error::Error;
+use crate::ip_onchain_runtime;
+use crate::signer::Signer;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use subxt::client::RuntimeVersion;
+use subxt::config::transaction_extensions::CheckMortalityParams;
+use subxt::config::DefaultExtrinsicParamsBuilder;
+use subxt::ext::codec::Decode;
+use subxt::tx::{Payload, SubmittableTransaction};
+use subxt::utils::{AccountId32, H256};
+use subxt::{Metadata, OfflineClient, OnlineClient, PolkadotConfig};
+
+/// Unsigned transaction written by `tx build`, it holds everything `tx sign` needs.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct UnsignedTx {
+    pub account_id: AccountId32,
+    pub call_data: String,
+    pub nonce: u64,
+    pub tip: u128,
+    /// None for an immortal transaction.
+    pub mortality: Option<Mortality>,
+    pub genesis_hash: H256,
+    pub spec_version: u32,
+    pub transaction_version: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Mortality {
+    pub period: u64,
+    pub block_number: u64,
+    pub block_hash: H256,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SignedTx {
+    pub account_id: AccountId32,
+    pub tx_hash: H256,
+    pub extrinsic: String,
+}
+
+/// Signer used by `tx build`, writes the unsigned transaction instead of signing it.
+#[derive(Clone)]
+pub struct OfflineSigner {
+    pub account_id: AccountId32,
+    pub output: PathBuf,
+    pub nonce: Option<u64>,
+    pub era_period: u64,
+}
+
+/// Call already encoded, signed as is.
+pub(crate) struct RawCall(pub Vec<u8>);
+
+impl Payload for RawCall {
+    fn encode_call_data_to(
+        &self,
+        _metadata: &Metadata,
+        out: &mut Vec<u8>,
+    ) -> Result<(), subxt::ext::subxt_core::Error> {
+        out.extend_from_slice(&self.0);
+        Ok(())
+    }
+}
+
+impl OfflineSigner {
+    pub async fn build<Call: Payload>(
+        &self,
+        api: &OnlineClient<PolkadotConfig>,
+        call: &Call,
+    ) -> Result<UnsignedTx, Error> {
+        let call_data = api
+            .tx()
+            .call_data(call)
+            .map_err(|e| Error::Decode(format!("can not encode call: {e}")))?;
+
+        let nonce = match self.nonce {
+            Some(nonce) => nonce,
+            None => api
+                .tx()
+                .account_nonce(&self.account_id)
+                .await
+                .map_err(|e| Error::Rpc(format!("account nonce: {e}")))?,
+        };
+
+        let mortality = match self.era_period {
+            0 => None,
+            period => {
+                let block = api
+                    .blocks()
+                    .at_latest()
+                    .await
+                    .map_err(|e| Error::Rpc(format!("latest block: {e}")))?;
+                Some(Mortality {
+                    period,
+                    block_number: block.number().into(),
+                    block_hash: block.hash(),
+                })
+            }
+        };
+
+        let version = api.runtime_version();
+        Ok(UnsignedTx {
+            account_id: self.account_id.clone(),
+            call_data: format!("0x{}", hex::encode(call_data)),
+            nonce,
+            tip: 0,
+            mortality,
+            genesis_hash: api.genesis_hash(),
+            spec_version: version.spec_version,
+            transaction_version: version.transaction_version,
+        })
+    }
+
+    /// Writes the unsigned transaction, returns the file written.
+    pub async fn write<Call: Payload>(
+        &self,
+        api: &OnlineClient<PolkadotConfig>,
+        call: &Call,
+    ) -> Result<PathBuf, Error> {
+        let unsigned = self.build(api, call).await?;
+        write_json(&self.output, &unsigned)?;
+        Ok(self.output.clone())
+    }
+}
+
+fn read_json<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<T, Error> {
+    let data = std::fs::read_to_string(path)
+        .map_err(|e| Error::Config(format!("can not read {}: {e}", path.display())))?;
+    serde_json::from_str(&data).map_err(|e| Error::Decode(format!("bad {}: {e}", path.display())))
+}
+
+fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<(), Error> {
+    let data = serde_json::to_string_pretty(value)?;
+    std::fs::write(path, data)
+        .map_err(|e| Error::Config(format!("can not write {}: {e}", path.display())))?;
+    Ok(())
+}
+
+fn decode_hex(name: &str, value: &str) -> Result<Vec<u8>, Error> {
+    hex::decode(value.trim_start_matches("0x"))
+        .map_err(|e| Error::Decode(format!("bad {name}: {e}")))
+}
+
+/// Metadata the client is generated from, so signing needs no node.
+fn bundled_metadata() -> Result<Metadata, Error> {
+    let bytes = include_bytes!("../ip_onchain_metadata.scale");
+    Metadata::decode(&mut &bytes[..]).map_err(|e| Error::Decode(format!("bundled metadata: {e}")))
+}
+
+/// Signs the unsigned transaction without any connection to the node.
+pub fn sign_unsigned(unsigned: &UnsignedTx, signer: &Signer) -> Result<SignedTx, Error> {
+    let account_id = signer.account_id();
+    if account_id != unsigned.account_id {
+        return Err(Error::Key(format!(
+            "tx is built for {} but the signer is {account_id}",
+            unsigned.account_id
+        )));
+    }
+
+    let client = OfflineClient::<PolkadotConfig>::new(
+        unsigned.genesis_hash,
+        RuntimeVersion {
+            spec_version: unsigned.spec_version,
+            transaction_version: unsigned.transaction_version,
+        },
+        bundled_metadata()?,
+    );
+
+    let mut params = DefaultExtrinsicParamsBuilder::<PolkadotConfig>::new()
+        .nonce(unsigned.nonce)
+        .tip(unsigned.tip)
+        .build();
+    // the builder only makes a tx mortal from the latest block, known to an online client,
+    // so the CheckMortality params (6th extension) are set with the block of `tx build`
+    let mortality: &mut CheckMortalityParams<PolkadotConfig> = &mut params.5;
+    *mortality = match &unsigned.mortality {
+        Some(m) => {
+            CheckMortalityParams::mortal_from_unchecked(m.period, m.block_number, m.block_hash)
+        }
+        None => CheckMortalityParams::immortal(),
+    };
+
+    let call = RawCall(decode_hex("call_data", &unsigned.call_data)?);
+    let mut partial = client
+        .tx()
+        .create_partial_offline(&call, params)
+        .map_err(|e| Error::Rpc(format!("can not create tx: {e}")))?;
+    let signature = signer.sign(&partial.signer_payload())?;
+    let tx = partial.sign_with_account_and_signature(&account_id, &signature);
+
+    Ok(SignedTx {
+        account_id,
+        tx_hash: tx.hash(),
+        extrinsic: format!("0x{}", hex::encode(tx.encoded())),
+    })
+}
+
+pub fn tx_sign(input: &Path, output: &Path, signer: &Signer) -> Result<(), Error> {
+    let unsigned: UnsignedTx = read_json(input)?;
+
+    // shown before signing, so the call can be checked on the offline machine
+    let call_data = decode_hex("call_data", &unsigned.call_data)?;
+    match ip_onchain_runtime::Call::decode(&mut &call_data[..]) {
+        Ok(call) => eprintln!("Call: {call:?}"),
+        Err(_) => eprintln!("Call: {}", unsigned.call_data),
+    }
+    eprintln!(
+        "Account: {}, nonce: {}, spec version: {}",
+        unsigned.account_id, unsigned.nonce, unsigned.spec_version
+    );
+
+    let signed = sign_unsigned(&unsigned, signer)?;
+    write_json(output, &signed)?;
+    println!(
+        "Signed tx {:?} written to {}",
+        signed.tx_hash,
+        output.display()
+    );
+    Ok(())
+}
+
+/// Broadcasts the signed tx, or only applies it on the latest block with `dry_run`.
+pub async fn tx_submit(client: &MubertClient, input: &Path, dry_run: bool) -> Result<(), Error> {
+    let signed: SignedTx = read_json(input)?;
+    let extrinsic = decode_hex("extrinsic", &signed.extrinsic)?;
+    let tx = SubmittableTransaction::from_bytes(client.api().clone(), extrinsic);
+
+    if dry_run {
+        let outcome =
+            TxOutcome::<Events>::DryRun(crate::dry_run::dry_run(client.api(), &tx).await?);
+        outcome.print_unsent()?;
+        return Ok(());
+    }
+
+    println!("Submitting tx {:?}...", signed.tx_hash);
+    let progress = tx
+        .submit_and_watch()
+        .await
+        .map_err(|e| Error::Rpc(format!("can not submit tx: {e}")))?;
+
+    println!("wait finalization...");
+    print_events(&client.wait_events(progress).await?)
+}
+
+/// Prints all the events of the finalized tx.
+pub(crate) fn print_events(events: &Events) -> Result<(), Error> {
+    for event in events.iter() {
+        let event = event?;
+        println!(
+            "{}.{}: {}",
+            event.pallet_name(),
+            event.variant_name(),
+            event.field_values()?
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dispatch::TxOutcome;
+    use crate::ip_onchain_runtime::runtime_types::bounded_collections::bounded_vec::BoundedVec;
+    use crate::signer::{Scheme, SignerArgs};
+    use std::str::FromStr;
+    use subxt::ext::codec::{Compact, Encode};
+    use subxt::utils::Era;
+    use subxt_signer::SecretUri;
+
+    fn unsigned(account_id: AccountId32) -> UnsignedTx {
+        let call = ip_onchain_runtime::tx().ip_onchain().create_author(
+            BoundedVec(b"alice".to_vec()),
+            None,
+            None,
+        );
+        let call_data = bundled_metadata()
+            .map(|metadata| call.encode_call_data(&metadata).unwrap())
+            .unwrap();
+        UnsignedTx {
+            account_id,
+            call_data: format!("0x{}", hex::encode(call_data)),
+            nonce: 3,
+            tip: 0,
+            mortality: Some(Mortality {
+                period: 64,
+                block_number: 100,
+                block_hash: H256::repeat_byte(1),
+            }),
+            genesis_hash: H256::repeat_byte(2),
+            spec_version: 1,
+            transaction_version: 1,
+        }
+    }
+
+    #[test]
+    fn test_sign_offline() {
+        let alice =
+            Signer::from_uri(&SecretUri::from_str("//Alice").unwrap(), Scheme::Sr25519).unwrap();
+        let unsigned = unsigned(alice.account_id());
+
+        let json = serde_json::to_string(&unsigned).unwrap();
+        assert_eq!(serde_json::from_str::<UnsignedTx>(&json).unwrap(), unsigned);
+
+        let signed = sign_unsigned(&unsigned, &alice).unwrap();
+        assert_eq!(signed.account_id, alice.account_id());
+        let extrinsic = decode_hex("extrinsic", &signed.extrinsic).unwrap();
+        assert!(extrinsic.ends_with(&decode_hex("call_data", &unsigned.call_data).unwrap()));
+
+        let bob =
+            Signer::from_uri(&SecretUri::from_str("//Bob").unwrap(), Scheme::Sr25519).unwrap();
+        assert!(sign_unsigned(&unsigned, &bob).is_err());
+    }
+
+    #[test]
+    fn test_sign_offline_mortality() {
+        let alice =
+            Signer::from_uri(&SecretUri::from_str("//Alice").unwrap(), Scheme::Sr25519).unwrap();
+        // after the length, the version, the account and the sr25519 signature
+        let era = |unsigned: &UnsignedTx| {
+            let signed = sign_unsigned(unsigned, &alice).unwrap();
+            let extrinsic = decode_hex("extrinsic", &signed.extrinsic).unwrap();
+            let start = Compact::<u32>::decode(&mut &extrinsic[..])
+                .unwrap()
+                .encoded_size()
+                + 1
+                + 33
+                + 65;
+            extrinsic[start..].to_vec()
+        };
+
+        let mut unsigned = unsigned(alice.account_id());
+        assert!(era(&unsigned).starts_with(&Era::mortal(64, 100).encode()));
+
+        unsigned.mortality = None;
+        assert!(era(&unsigned).starts_with(&Era::Immortal.encode()));
+    }
+
+    /// Needs a dev node with the Proxy pallet, like `mubert-node --dev`:
+    /// `MUBERT_TEST_NODE_URL=ws://127.0.0.1:9944 cargo test -- --ignored`
+    #[tokio::test]
+    #[ignore]
+    async fn test_dev_chain_build_via_proxy() {
+        let node_url = std::env::var("MUBERT_TEST_NODE_URL")
+            .unwrap_or_else(|_| "ws://127.0.0.1:9944".to_string());
+        let api = OnlineClient::<PolkadotConfig>::from_url(&node_url)
+            .await
+            .unwrap();
+        let dev = |name: &str| {
+            Signer::from_uri(&SecretUri::from_str(name).unwrap(), Scheme::Sr25519).unwrap()
+        };
+
+        let output = std::env::temp_dir().join(format!("mubert-tx-{}.json", std::process::id()));
+        let offline = OfflineSigner {
+            account_id: dev("//Alice").account_id(),
+            output: output.clone(),
+            nonce: Some(0),
+            era_period: 0,
+        };
+        let mut args = SignerArgs::default();
+        args.wrap.via_proxy = Some(dev("//Bob").account_id());
+        let signer = args.offline_signer(&offline).unwrap();
+
+        let call = ip_onchain_runtime::tx().ip_onchain().create_author(
+            BoundedVec(b"alice".to_vec()),
+            None,
+            None,
+        );
+        let outcome = signer
+            .create_signed(&api, &call, Default::default())
+            .await
+            .unwrap();
+        assert!(matches!(outcome, TxOutcome::Built(ref path) if *path == output));
+
+        let unsigned: UnsignedTx = read_json(&output).unwrap();
+        let call_data = decode_hex("call_data", &unsigned.call_data).unwrap();
+        let proxy = api.metadata().pallet_by_name("Proxy").unwrap().index();
+        assert_eq!(call_data[0], proxy);
+        assert!(call_data.ends_with(&api.tx().call_data(&call).unwrap()));
+        std::fs::remove_file(&output).unwrap();
+    }
+}
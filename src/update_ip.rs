@@ -1,39 +1,174 @@
 use crate::api::MetadataRequest;
+use crate::arweave::{DEFAULT_AMOUNT, DEFAULT_TIPS};
+use crate::block::{At, AtBlock};
+use crate::client::{find_event, Events, MubertClient};
+use crate::dispatch::TxOutcome;
+use crate::error::Error;
 use crate::ip_onchain_runtime::ip_onchain::calls::types::create_entity::{
     MetadataFeatures, MetadataStandard,
 };
+use crate::ip_onchain_runtime::ip_onchain::calls::types::CreateEntity;
+use crate::ip_onchain_runtime::ip_onchain::events::{EntityAdded, EntityEdited};
+use crate::ip_onchain_runtime::ip_onchain::storage::types::entities::Entities;
 use crate::ip_onchain_runtime::runtime_types::bounded_collections::bounded_vec::BoundedVec;
-use crate::ip_onchain_runtime::runtime_types::pallet_arweave::types::TaskState;
 use crate::ip_onchain_runtime::runtime_types::pallet_ip_onchain::types::{
     BitFlags, IPEntityKind, MetadataFeature, Wallet,
 };
-use crate::{api, calculate_flags, ip_onchain_runtime, SecretKeyFile};
+use crate::ip_onchain_runtime::runtime_types::pallet_nfts;
+use crate::ip_onchain_runtime::runtime_types::pallet_nfts::types::{ItemConfig, ItemSetting};
+use crate::journal::{ResumeArgs, UploadJournal};
+use crate::upload::{UploadProgress, MIB};
+use crate::{has_flag, ip_onchain_runtime, parse_flags};
 use serde::{Deserialize, Serialize};
-use std::error::Error;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use subxt::tx::DefaultPayload;
 use subxt::utils::AccountId32;
-use subxt::{OnlineClient, PolkadotConfig};
-use subxt_signer::bip39::Mnemonic;
-use subxt_signer::sr25519::{dev, Keypair};
-use tokio_retry::strategy::FixedInterval;
-use tokio_retry::Retry;
+use subxt::{Metadata, OnlineClient, PolkadotConfig};
+
+pub type EntityDetails = Entities;
 
 #[derive(Serialize, Deserialize)]
-struct CreateEntityFields {
-    entity_kind: IPEntityKind,
-    authority_id: u32,
-    metadata_standard: MetadataStandard,
-    flags: Vec<String>,
-    authors_ids: Option<BoundedVec<u32>>,
-    royalty_parts: Option<BoundedVec<Wallet<AccountId32>>>,
-    related_entities_ids: Option<BoundedVec<u32>>,
-
-    off_chain_metadata: OffChainMetadata,
-    metadata_url: Option<String>,
+pub struct CreateEntityFields {
+    pub entity_kind: IPEntityKind,
+    pub authority_id: u32,
+    pub metadata_standard: MetadataStandard,
+    pub flags: Vec<String>,
+    pub authors_ids: Option<BoundedVec<u32>>,
+    pub royalty_parts: Option<BoundedVec<Wallet<AccountId32>>>,
+    pub related_entities_ids: Option<BoundedVec<u32>>,
+
+    pub off_chain_metadata: OffChainMetadata,
+    pub metadata_url: Option<String>,
+    pub nft: Option<NftFields>,
+}
+
+impl CreateEntityFields {
+    pub fn metadata_request(&self, fingerprint: String) -> MetadataRequest {
+        MetadataRequest {
+            title: self.off_chain_metadata.title.clone(),
+            bpm: self.off_chain_metadata.bpm,
+            key: self.off_chain_metadata.key,
+            scale: self.off_chain_metadata.scale,
+            instrument: self.off_chain_metadata.instrument,
+            fingerprint,
+        }
+    }
+
+    /// Checks the item settings and that the authority has an nft collection to mint
+    /// the entity in.
+    pub(crate) async fn check_nft_collection(
+        &self,
+        api: &OnlineClient<PolkadotConfig>,
+    ) -> Result<(), Error> {
+        let Some(nft) = &self.nft else {
+            return Ok(());
+        };
+        nft.item_config()?;
+
+        let query = ip_onchain_runtime::storage()
+            .ip_onchain()
+            .authorities(self.authority_id);
+
+        let authority = api
+            .storage()
+            .at_latest()
+            .await?
+            .fetch(&query)
+            .await?
+            .ok_or_else(|| Error::NotFound("authority not found".into()))?;
+
+        if authority.collection_id.is_none() {
+            return Err(Error::Config(format!(
+                "authority {} has no nft collection, init it with edit-authority",
+                self.authority_id
+            )));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn into_call(
+        self,
+        metadata_url: String,
+    ) -> Result<DefaultPayload<CreateEntity>, Error> {
+        let flags: MetadataFeatures = MetadataFeatures::from(BitFlags(
+            parse_flags::<MetadataFeature>(&self.flags)?,
+            Default::default(),
+        ));
+
+        let (nft_item_id, nft_owner, nft_item_config) = match self.nft {
+            Some(nft) => (
+                Some(nft.item_id),
+                Some(nft.owner.clone()),
+                Some(nft.item_config()?),
+            ),
+            None => (None, None, None),
+        };
+
+        Ok(ip_onchain_runtime::tx().ip_onchain().create_entity(
+            self.entity_kind,
+            self.authority_id,
+            metadata_url.into(),
+            self.metadata_standard,
+            flags,
+            self.authors_ids,
+            self.royalty_parts,
+            self.related_entities_ids,
+            nft_item_id,
+            nft_owner,
+            nft_item_config,
+        ))
+    }
 }
 
 #[derive(Serialize, Deserialize)]
-struct OffChainMetadata {
+pub struct NftFields {
+    pub item_id: u32,
+    pub owner: AccountId32,
+    /// Disabled item settings, like the set bits of pallet_nfts: `Transferable` makes
+    /// the item non transferable.
+    #[serde(default)]
+    pub item_settings: Vec<String>,
+}
+
+impl NftFields {
+    fn item_config(&self) -> Result<ItemConfig, Error> {
+        Ok(ItemConfig {
+            settings: pallet_nfts::types::BitFlags(
+                parse_flags::<ItemSetting>(&self.item_settings)?,
+                Default::default(),
+            ),
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct EditEntityFields {
+    pub authority_id: Option<u32>,
+    pub metadata_standard: Option<MetadataStandard>,
+    pub flags: Option<Vec<String>>,
+    pub authors_ids: Option<BoundedVec<u32>>,
+    pub royalty_parts: Option<BoundedVec<Wallet<AccountId32>>>,
+    pub related_entities_ids: Option<BoundedVec<u32>>,
+
+    pub metadata_url: Option<String>,
+}
+
+impl EditEntityFields {
+    /// Metadata features replacing the current ones, an unknown name is an error
+    /// instead of clearing the features on chain.
+    fn flags(&self) -> Result<Option<MetadataFeatures>, Error> {
+        let Some(flags) = &self.flags else {
+            return Ok(None);
+        };
+        Ok(Some(MetadataFeatures::from(BitFlags(
+            parse_flags::<MetadataFeature>(flags)?,
+            Default::default(),
+        ))))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct OffChainMetadata {
     pub title: String,
     pub bpm: u32,
     pub key: u8,
@@ -41,244 +176,489 @@ struct OffChainMetadata {
     pub instrument: u8,
 }
 
-pub async fn update_ip(
-    node_url: &String,
-    api_auth: &String,
-    file: &PathBuf,
-    data: &Option<String>,
-    data_file: &Option<PathBuf>,
-    secret_key_file: &Option<PathBuf>,
-    arweave_worker_address: &Option<AccountId32>,
-) -> Result<(), Box<dyn Error>> {
-    // parsing a arguments
-    let data = match (data, data_file) {
+/// Result of `create_entity`.
+#[derive(Debug)]
+pub struct EntityCreated {
+    pub entity: EntityAdded,
+    /// Fields of the pallet_nfts `Issued` event when the entity is minted as nft, the pallet
+    /// is found by its event type in the runtime metadata.
+    pub nft_issued: Option<String>,
+}
+
+fn read_data(data: &Option<String>, data_file: &Option<PathBuf>) -> Result<String, Error> {
+    match (data, data_file) {
         (Some(data), None) => Ok(data.to_string()),
-        (None, Some(data_file)) => {
-            let data_file = std::fs::read_to_string(data_file)
-                .map_err(|e| format!("read data_file {:?}: {e}", data_file))?;
-            Ok(data_file)
-        }
-        _ => Err("no data file given"),
-    }?;
-
-    let req: CreateEntityFields =
-        serde_json::from_str(data.as_str()).map_err(|e| format!("parsing json: {e}"))?;
-
-    let flags: MetadataFeatures = MetadataFeatures::from(BitFlags(
-        calculate_flags::<MetadataFeature>(req.flags),
-        Default::default(),
-    ));
-
-    let mut sender_keypair = dev::alice();
-
-    if let Some(secret_key_file) = secret_key_file {
-        let secret_key_data = std::fs::read_to_string(secret_key_file)
-            .map_err(|e| format!("read secret_key_file {:?}: {e}", secret_key_file))?;
-        let secret_key: SecretKeyFile = serde_json::from_str(secret_key_data.as_str())
-            .map_err(|e| format!("parsing json: {e}"))?;
-        let mnemonic = Mnemonic::parse(secret_key.secret_phrase).unwrap();
-        sender_keypair = Keypair::from_phrase(&mnemonic, None).unwrap();
+        (None, Some(data_file)) => std::fs::read_to_string(data_file)
+            .map_err(|e| Error::Config(format!("read data_file {:?}: {e}", data_file))),
+        _ => Err(Error::Config("no data file given".into())),
     }
+}
 
-    // get rpc api client
-    let api = OnlineClient::<PolkadotConfig>::from_url(node_url)
-        .await
-        .map_err(|e| format!("chain rpc api: {e}"))?;
+/// Index of the pallet whose event enum is the type at `path`, whatever its name in the runtime.
+fn pallet_of_event(metadata: &Metadata, path: &[&str]) -> Option<u8> {
+    metadata
+        .pallets()
+        .find(|pallet| {
+            pallet
+                .event_ty_id()
+                .and_then(|id| metadata.types().resolve(id))
+                .is_some_and(|ty| ty.path.segments == path)
+        })
+        .map(|pallet| pallet.index())
+}
 
-    let metadata_url = match req.metadata_url {
-        Some(off_chain_metadata_url) => off_chain_metadata_url,
-        None => {
-            // make off chain requests
-            let job = api::upload_audio(file, api_auth)
-                .await
-                .map_err(|e| format!("upload_audio_fingerprint: {e}"))?;
-            println!("fingerprint worker job id: {}", job.id);
-
-            let fingerprint = api::wait_for_fingerprint_url(&job.id, 10, api_auth)
-                .await
-                .map_err(|e| format!("wait_for_fingerprint_url: {e}"))?;
-            println!("fingerprint: {fingerprint}");
+fn entity_created(metadata: &Metadata, events: &Events, nft: bool) -> Result<EntityCreated, Error> {
+    let entity = find_event(events)?;
+    if !nft {
+        return Ok(EntityCreated {
+            entity,
+            nft_issued: None,
+        });
+    }
 
-            let metadata_req = api::MetadataRequest {
-                title: req.off_chain_metadata.title,
-                bpm: req.off_chain_metadata.bpm,
-                key: req.off_chain_metadata.key,
-                scale: req.off_chain_metadata.scale,
-                instrument: req.off_chain_metadata.instrument,
-                fingerprint,
-            };
+    // pallet_nfts is not part of the bundled metadata, decode its events dynamically
+    let nfts = pallet_of_event(metadata, &["pallet_nfts", "pallet", "Event"]).ok_or_else(|| {
+        Error::NotFound("tx submitted, but the runtime has no pallet_nfts events".into())
+    })?;
+    for event in events.iter() {
+        let event = event
+            .map_err(|e| Error::Decode(format!("tx submitted, but can not decode event: {e}")))?;
+        if event.pallet_index() == nfts && event.variant_name() == "Issued" {
+            return Ok(EntityCreated {
+                entity,
+                nft_issued: Some(event.field_values()?.to_string()),
+            });
+        }
+    }
+    Err(Error::NotFound(
+        "tx submitted, but pallet_nfts Issued event not found".into(),
+    ))
+}
 
-            if let Some(arweave_worker_address) = arweave_worker_address {
-                let metadata_url = upload_metadata_to_arweave(
-                    &mut sender_keypair,
-                    &api,
-                    &metadata_req,
-                    arweave_worker_address,
-                )
-                .await?;
-                println!("Done! Arweave metadata url: {}", metadata_url);
+fn check_editable(entity_id: u32, entity: &EntityDetails) -> Result<(), Error> {
+    if has_flag(entity.metadata.features.0, MetadataFeature::Immutable) {
+        return Err(Error::Config(format!(
+            "entity {entity_id} is Immutable and can not be edited"
+        )));
+    }
+    Ok(())
+}
 
-                metadata_url
-            } else {
-                let off_chain_metadata = api::create_metadata(metadata_req, api_auth)
-                    .await
-                    .map_err(|e| format!("create_metadata: {e}"))?;
-                println!("off chain metadata url: {}", off_chain_metadata.url);
+impl MubertClient {
+    /// Creates the arweave task storing the metadata, its url is known once validated.
+    pub async fn create_metadata_task(
+        &self,
+        metadata_req: &MetadataRequest,
+        arweave_worker_address: &AccountId32,
+    ) -> Result<TxOutcome<u64>, Error> {
+        self.create_arweave_task(
+            arweave_worker_address,
+            serde_json::to_vec(metadata_req)?,
+            DEFAULT_AMOUNT,
+            DEFAULT_TIPS,
+        )
+        .await
+    }
 
-                off_chain_metadata.url
-            }
+    /// Stores the metadata on arweave through the worker and waits for its url.
+    pub async fn upload_metadata_to_arweave(
+        &self,
+        metadata_req: &MetadataRequest,
+        arweave_worker_address: &AccountId32,
+    ) -> Result<TxOutcome<String>, Error> {
+        let task_id = self
+            .create_metadata_task(metadata_req, arweave_worker_address)
+            .await?
+            .into_submitted();
+        match task_id {
+            Ok(task_id) => Ok(TxOutcome::Submitted(
+                self.wait_for_arweave_url(task_id).await?,
+            )),
+            Err(outcome) => Ok(outcome),
         }
-    };
-
-    let call = ip_onchain_runtime::tx().ip_onchain().create_entity(
-        req.entity_kind,
-        req.authority_id,
-        metadata_url.into(),
-        req.metadata_standard,
-        flags,
-        req.authors_ids,
-        req.royalty_parts,
-        req.related_entities_ids,
-        None,
-        None,
-        None,
-    );
+    }
 
-    println!("Submitting transaction...");
-    let tx_progress = api
-        .tx()
-        .sign_and_submit_then_watch_default(&call, &sender_keypair)
-        .await
-        .map_err(|e| format!("can not submit tx: {e}"))?;
+    pub async fn create_entity(
+        &self,
+        fields: CreateEntityFields,
+        metadata_url: String,
+    ) -> Result<TxOutcome<EntityCreated>, Error> {
+        fields.check_nft_collection(self.api()).await?;
+        let nft = fields.nft.is_some();
+        self.submit(&fields.into_call(metadata_url)?)
+            .await?
+            .and_then(|events| entity_created(&self.api().metadata(), &events, nft))
+    }
 
-    println!("wait finalization...");
-    let finalized = tx_progress
-        .wait_for_finalized()
-        .await
-        .map_err(|e| format!("can not finalize tx: {e}"))?;
+    pub async fn get_entity(
+        &self,
+        entity_id: u32,
+        at: &Option<At>,
+    ) -> Result<AtBlock<EntityDetails>, Error> {
+        let block = self.block(at).await?;
+        let query = ip_onchain_runtime::storage()
+            .ip_onchain()
+            .entities(entity_id);
+
+        let details = block
+            .storage()
+            .fetch(&query)
+            .await?
+            .ok_or_else(|| Error::NotFound("entity not found".into()))?;
+        Ok(AtBlock::new(&block, details))
+    }
 
-    println!("wait events...");
-    let events = finalized
-        .fetch_events()
-        .await
-        .map_err(|e| format!("tx submitted, but not can not fetch events: {e}"))?;
-
-    // check events
-    if let Some(event) = events
-        .find_first::<ip_onchain_runtime::ip_onchain::events::EntityAdded>()
-        .map_err(|e| format!("tx submitted, but event not found: {e}"))?
-    {
-        println!("Entity added successful: {:?}", event);
+    pub async fn edit_entity(
+        &self,
+        entity_id: u32,
+        fields: EditEntityFields,
+    ) -> Result<TxOutcome<EntityEdited>, Error> {
+        let current = self.get_entity(entity_id, &None).await?;
+        check_editable(entity_id, &current.details)?;
+
+        let call = ip_onchain_runtime::tx().ip_onchain().edit_entity(
+            entity_id,
+            fields.metadata_url.clone().map(Into::into),
+            fields.metadata_standard.clone(),
+            fields.flags()?,
+            fields.authority_id,
+            fields.authors_ids,
+            fields.royalty_parts,
+            fields.related_entities_ids,
+            None,
+            None,
+            None,
+        );
+        self.submit(&call)
+            .await?
+            .and_then(|events| find_event(&events))
     }
-    Ok(())
 }
 
-async fn upload_metadata_to_arweave(
-    sender_keypair: &mut Keypair,
-    api: &OnlineClient<PolkadotConfig>,
-    metadata_req: &MetadataRequest,
-    arweave_worker_address: &AccountId32,
-) -> Result<String, Box<dyn Error>> {
-    println!("Starting arweave metadata upload");
-
-    let data = serde_json::to_string(&metadata_req).unwrap();
-    let call = ip_onchain_runtime::tx().arweave().create_task(
-        arweave_worker_address.clone(),
-        BoundedVec::from(data),
-        1000000u128,
-        1000u128,
+fn print_upload_progress(progress: UploadProgress) {
+    let mib = |bytes: u64| bytes as f64 / MIB as f64;
+    eprint!(
+        "\ruploaded {:.1}/{:.1} MiB ({}%)",
+        mib(progress.sent),
+        mib(progress.total),
+        (progress.sent * 100)
+            .checked_div(progress.total)
+            .unwrap_or(100)
     );
+}
+
+pub async fn update_ip(
+    client: &MubertClient,
+    file: &Path,
+    data: &Option<String>,
+    data_file: &Option<PathBuf>,
+    arweave_worker_address: &Option<AccountId32>,
+    resume: &ResumeArgs,
+) -> Result<(), Error> {
+    let data = read_data(data, data_file)?;
+    let req: CreateEntityFields = serde_json::from_str(data.as_str())
+        .map_err(|e| Error::Decode(format!("parsing json: {e}")))?;
+
+    req.check_nft_collection(client.api()).await?;
+
+    let journal_path = match &resume.journal {
+        Some(path) => path.clone(),
+        None => UploadJournal::default_path(file, &data)?,
+    };
+    let mut journal = UploadJournal::open(journal_path)?;
+    if journal.is_started() {
+        println!("Resuming from the journal {:?}", journal.path());
+    }
+    journal.resume(resume);
+
+    let metadata_url = match (&req.metadata_url, &journal.metadata_url) {
+        (Some(off_chain_metadata_url), _) => off_chain_metadata_url.clone(),
+        (None, Some(metadata_url)) => {
+            println!("metadata url from the journal: {metadata_url}");
+            metadata_url.clone()
+        }
+        (None, None) if !client.signer()?.sends_tx() => {
+            // the audio upload and the arweave task are real side effects, not dry run
+            return Err(Error::Config(
+                "upload-ip with --dry-run or tx build needs the metadata_url of the entity".into(),
+            ));
+        }
+        (None, None) => {
+            let metadata_url =
+                off_chain_metadata(client, &req, file, arweave_worker_address, &mut journal)
+                    .await?;
+            let Some(metadata_url) = metadata_url else {
+                return Ok(());
+            };
+            metadata_url
+        }
+    };
 
     println!("Submitting transaction...");
-    let submited = api
-        .tx()
-        .sign_and_submit_then_watch_default(&call, sender_keypair)
-        .await
-        .map_err(|e| format!("can not submit tx: {e}"))?;
+    let Some(created) = client
+        .create_entity(req, metadata_url)
+        .await?
+        .print_unsent()?
+    else {
+        return Ok(());
+    };
+    println!("Entity added successful: {:?}", created.entity);
+    if let Some(nft_issued) = created.nft_issued {
+        println!("Nft minted successful: {nft_issued}");
+    }
+    journal.remove()
+}
 
-    println!("wait finalization...");
-    let finalized = submited
-        .wait_for_finalized()
-        .await
-        .map_err(|e| format!("tx submitted, but not finalized: {e}"))?;
+/// Fingerprint of the audio, from the stage of the journal on.
+async fn fingerprint(
+    client: &MubertClient,
+    file: &Path,
+    journal: &mut UploadJournal,
+) -> Result<String, Error> {
+    if let Some(fingerprint) = &journal.fingerprint {
+        return Ok(fingerprint.clone());
+    }
+    let api = client.fingerprint_api();
 
-    println!("wait events...");
-    let events = finalized
-        .fetch_events()
-        .await
-        .map_err(|e| format!("tx submitted, but not can not fetch events: {e}"))?;
-
-    let task_id = match events
-        .find_first::<ip_onchain_runtime::arweave::events::TaskAdded>()
-        .map_err(|e| format!("tx submitted, but event not found: {e}"))?
-    {
-        Some(e) => {
-            println!("Task added successful: task_id={:?}", e.task_id);
-            e.task_id
+    let job_id = match &journal.fingerprint_job {
+        Some(job_id) => {
+            println!("Resuming fingerprint job {job_id}");
+            job_id.clone()
+        }
+        None => {
+            println!("Uploading audio to the fingerprint worker...");
+            let job = api
+                .upload_audio_with_progress(file, &print_upload_progress)
+                .await?;
+            eprintln!();
+            if job.reused {
+                println!(
+                    "same audio already uploaded, reusing fingerprint job {}",
+                    job.id
+                );
+            }
+            journal.fingerprint_job = Some(job.id.clone());
+            journal.save()?;
+            job.id
         }
-        None => return Err("unexpected event".into()),
     };
 
-    let tasks_query = ip_onchain_runtime::storage().arweave().tasks(task_id);
+    let fingerprint = match api.wait_for_fingerprint_url(&job_id).await {
+        Ok(fingerprint) => fingerprint,
+        Err(e @ Error::JobFailed(_)) => {
+            // the next run uploads the audio again
+            journal.fingerprint_job = None;
+            journal.save()?;
+            return Err(e);
+        }
+        Err(e) => return Err(e),
+    };
+    journal.fingerprint = Some(fingerprint.clone());
+    journal.save()?;
+    Ok(fingerprint)
+}
 
-    println!("Waiting for worker done task: may take a 5 min to validate");
+/// Off chain metadata url from the fingerprint api or the arweave worker, `None` when
+/// the arweave task tx is not sent, like with `--dry-run`.
+async fn off_chain_metadata(
+    client: &MubertClient,
+    req: &CreateEntityFields,
+    file: &Path,
+    arweave_worker_address: &Option<AccountId32>,
+    journal: &mut UploadJournal,
+) -> Result<Option<String>, Error> {
+    let task_id = match journal.arweave_task {
+        Some(task_id) => {
+            println!("Resuming arweave task {task_id}");
+            task_id
+        }
+        None => {
+            let fingerprint = fingerprint(client, file, journal).await?;
+            println!("fingerprint: {fingerprint}");
+            let metadata_req = req.metadata_request(fingerprint);
 
-    let result = Retry::spawn(FixedInterval::from_millis(6_000), || async {
-        println!("Get task state: task_id={}", task_id);
+            let Some(arweave_worker_address) = arweave_worker_address else {
+                let off_chain_metadata = client
+                    .fingerprint_api()
+                    .create_metadata(&metadata_req)
+                    .await?;
+                println!("off chain metadata url: {}", off_chain_metadata.url);
 
-        let tasks_details = api
-            .storage()
-            .at_latest()
-            .await
-            .unwrap()
-            .fetch(&tasks_query)
-            .await
-            .unwrap()
-            .ok_or("no task")
-            .unwrap();
+                journal.metadata_url = Some(off_chain_metadata.url.clone());
+                journal.save()?;
+                return Ok(Some(off_chain_metadata.url));
+            };
 
-        println!("state: {:?}", tasks_details.state);
-        if tasks_details.state != TaskState::Validate {
-            println!("retry");
-            return Err("task not in validate state, waiting");
-        };
+            println!("Starting arweave metadata upload, may take a 5 min to validate");
+            let task_id = client
+                .create_metadata_task(&metadata_req, arweave_worker_address)
+                .await?
+                .print_unsent()?;
+            let Some(task_id) = task_id else {
+                return Ok(None);
+            };
+            println!("arweave task id: {task_id}");
+            journal.arweave_task = Some(task_id);
+            journal.save()?;
+            task_id
+        }
+    };
 
-        if let Some(tx_hash) = tasks_details.tx_hash {
-            let metadata_url = format!(
-                "https://arweave.net/{}",
-                String::from_utf8(tx_hash.0).unwrap()
-            );
+    let metadata_url = client.wait_for_arweave_url(task_id).await?;
+    println!("Done! Arweave metadata url: {}", metadata_url);
+    journal.metadata_url = Some(metadata_url.clone());
+    journal.save()?;
+    Ok(Some(metadata_url))
+}
 
-            return Ok(metadata_url);
-        };
+pub async fn get_entity(
+    client: &MubertClient,
+    entity_id: u32,
+    at: &Option<At>,
+) -> Result<(), Error> {
+    let entity = client.get_entity(entity_id, at).await?;
+    println!("{}", serde_json::to_string(&entity)?);
+    Ok(())
+}
+
+pub async fn edit_entity(
+    client: &MubertClient,
+    entity_id: u32,
+    data: &Option<String>,
+    data_file: &Option<PathBuf>,
+) -> Result<(), Error> {
+    let req: EditEntityFields = serde_json::from_str(read_data(data, data_file)?.as_str())
+        .map_err(|e| Error::Decode(format!("parsing json: {e}")))?;
+
+    let current = client.get_entity(entity_id, &None).await?.details;
+    check_editable(entity_id, &current)?;
+
+    println!("Changes for entity {entity_id}:");
+    if let Some(url) = &req.metadata_url {
+        print_diff(
+            "metadata_url",
+            String::from_utf8_lossy(&current.metadata.url.0).as_ref(),
+            url.as_str(),
+        )?;
+    }
+    if let Some(standard) = &req.metadata_standard {
+        print_diff("metadata_standard", &current.metadata.standard, standard)?;
+    }
+    if let Some(flags) = &req.flags()? {
+        print_diff("flags", &current.metadata.features.0, &flags.0)?;
+    }
+    if let Some(authority_id) = &req.authority_id {
+        print_diff("authority_id", &current.owner, authority_id)?;
+    }
+    if req.authors_ids.is_some() {
+        print_diff("authors_ids", &current.authors, &req.authors_ids)?;
+    }
+    if req.royalty_parts.is_some() {
+        print_diff("royalty_parts", &current.royalty_parts, &req.royalty_parts)?;
+    }
+    if req.related_entities_ids.is_some() {
+        print_diff(
+            "related_entities_ids",
+            &current.related_to,
+            &req.related_entities_ids,
+        )?;
+    }
 
-        Err("task in unexpected state")
-    })
-    .await;
+    println!("Submitting transaction...");
+    let Some(event) = client.edit_entity(entity_id, req).await?.print_unsent()? else {
+        return Ok(());
+    };
+    println!("Entity edited successful: {:?}", event);
+    Ok(())
+}
+
+fn print_diff<T: Serialize + ?Sized>(name: &str, old: &T, new: &T) -> Result<(), Error> {
+    let old = serde_json::to_string(old)?;
+    let new = serde_json::to_string(new)?;
 
-    Ok(result.expect("can not get tx_hash from task"))
+    if old == new {
+        println!("  {name}: {old} (unchanged)");
+    } else {
+        println!("  {name}: {old} -> {new}");
+    }
+    Ok(())
 }
 
-pub async fn get_entity(node_url: &String, entity_id: u32) -> Result<(), Box<dyn Error>> {
-    let api = OnlineClient::<PolkadotConfig>::from_url(node_url)
-        .await
-        .map_err(|e| format!("chain rpc api: {e}"))?;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let query = ip_onchain_runtime::storage()
-        .ip_onchain()
-        .entities(entity_id);
+    fn example_fields() -> CreateEntityFields {
+        let path =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("examples/create_entity_nft.json");
+        serde_json::from_str(&read_data(&None, &Some(path)).unwrap()).unwrap()
+    }
 
-    let details = api
-        .storage()
-        .at_latest()
-        .await?
-        .fetch(&query)
-        .await?
-        .ok_or("entity not found")?;
+    #[test]
+    fn test_pallet_of_event() {
+        let metadata: Metadata = subxt::ext::codec::Decode::decode(
+            &mut &include_bytes!("../ip_onchain_metadata.scale")[..],
+        )
+        .unwrap();
+        let xcm = metadata.pallet_by_name("PolkadotXcm").unwrap().index();
+        assert_eq!(
+            pallet_of_event(&metadata, &["pallet_xcm", "pallet", "Event"]),
+            Some(xcm)
+        );
+        // not in the bundled metadata, only in the runtime of the node
+        assert_eq!(
+            pallet_of_event(&metadata, &["pallet_nfts", "pallet", "Event"]),
+            None
+        );
+    }
 
-    let data = serde_json::to_string(&details).unwrap();
+    #[test]
+    fn test_nft_item_settings_sent() {
+        let call = example_fields().into_call("url".into()).unwrap();
+        let item_config = call.call_data().nft_item_config.as_ref().unwrap();
+        // nothing disabled, the minted item is transferable
+        assert_eq!(item_config.settings.0, 0);
+
+        let mut fields = example_fields();
+        fields.nft.as_mut().unwrap().item_settings = vec!["Transferable".into()];
+        let call = fields.into_call("url".into()).unwrap();
+        let item_config = call.call_data().nft_item_config.as_ref().unwrap();
+        assert_eq!(item_config.settings.0, 0x00000001);
+
+        let mut fields = example_fields();
+        fields.nft.as_mut().unwrap().item_settings = vec!["transferable".into()];
+        assert!(matches!(
+            fields.into_call("url".into()),
+            Err(Error::Config(_))
+        ));
+    }
 
-    println!("{data}");
+    #[test]
+    fn test_edit_entity_flags() {
+        let fields = |flags: Option<Vec<String>>| EditEntityFields {
+            authority_id: None,
+            metadata_standard: None,
+            flags,
+            authors_ids: None,
+            royalty_parts: None,
+            related_entities_ids: None,
+            metadata_url: None,
+        };
 
-    Ok(())
+        assert!(fields(None).flags().unwrap().is_none());
+        let flags = fields(Some(vec![])).flags().unwrap().unwrap();
+        assert_eq!(flags.0, 0);
+        let flags = fields(Some(vec!["Immutable".into()]))
+            .flags()
+            .unwrap()
+            .unwrap();
+        assert_eq!(flags.0, 0x00000001);
+
+        // a typo must not clear the features on chain
+        let result = fields(Some(vec!["Immutible".into()])).flags();
+        assert!(matches!(
+            result,
+            Err(Error::Config(ref e)) if e == "unknown MetadataFeature \"Immutible\""
+        ));
+    }
 }
@@ -0,0 +1,444 @@
+use crate::api::{status_error, FingerprintApi, JobResponse};
+use crate::error::Error;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+pub const MIB: u64 = 1024 * 1024;
+
+/// How the audio is sent to the fingerprint api.
+#[derive(Clone, Debug)]
+pub struct UploadOptions {
+    /// Size of the chunks of a resumable upload.
+    pub chunk_size: u64,
+    /// Retries of a request failing with a connection error, a 429 or a 5xx status.
+    pub retries: u32,
+    /// Delay before the first retry, doubled after every retry.
+    pub retry_delay: Duration,
+    /// Json file with the fingerprint jobs of the uploaded files by content hash, not kept if not set.
+    pub job_cache: Option<PathBuf>,
+    /// Sends the file by chunks through the upload sessions of the api, when it has them.
+    pub resumable: bool,
+}
+
+impl Default for UploadOptions {
+    fn default() -> Self {
+        UploadOptions {
+            chunk_size: 8 * MIB,
+            retries: 5,
+            retry_delay: Duration::from_secs(1),
+            job_cache: None,
+            resumable: false,
+        }
+    }
+}
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct UploadArgs {
+    #[arg(
+        long,
+        default_value_t = 8,
+        help = "chunk size in MiB of a resumable upload"
+    )]
+    pub chunk_size: u64,
+    #[arg(
+        long,
+        default_value_t = 5,
+        help = "retries of an upload request failing with a transient error"
+    )]
+    pub upload_retries: u32,
+    #[arg(
+        long,
+        help = "upload a file again even if its content was already fingerprinted"
+    )]
+    pub no_job_cache: bool,
+    #[arg(
+        long,
+        help = "send the audio by chunks through the upload sessions of the api, resumed after a dropped connection"
+    )]
+    pub resumable_upload: bool,
+}
+
+impl UploadArgs {
+    /// The job cache is `<cache dir>/mubert-cli/fingerprint_jobs.json`.
+    pub fn options(&self) -> Result<UploadOptions, Error> {
+        let job_cache = match self.no_job_cache {
+            true => None,
+            false => Some(
+                dirs::cache_dir()
+                    .ok_or_else(|| Error::Config("no cache dir, set --no-job-cache".into()))?
+                    .join("mubert-cli")
+                    .join("fingerprint_jobs.json"),
+            ),
+        };
+        Ok(UploadOptions {
+            chunk_size: self.chunk_size.max(1) * MIB,
+            retries: self.upload_retries,
+            job_cache,
+            resumable: self.resumable_upload,
+            ..Default::default()
+        })
+    }
+}
+
+/// Bytes of the file sent so far.
+#[derive(Clone, Copy, Debug)]
+pub struct UploadProgress {
+    pub sent: u64,
+    pub total: u64,
+}
+
+/// Resumable upload of a file, the api keeps it by content hash so a new session continues it.
+#[derive(Deserialize, Debug)]
+struct UploadSession {
+    upload_id: String,
+    offset: u64,
+    /// Job of an upload already complete.
+    id: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ChunkResponse {
+    offset: u64,
+    id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct JobCache {
+    jobs: BTreeMap<String, CachedJob>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedJob {
+    api_url: String,
+    job_id: String,
+}
+
+impl JobCache {
+    fn load(path: &Path) -> Result<Self, Error> {
+        if !path.exists() {
+            return Ok(JobCache::default());
+        }
+        let data = std::fs::read_to_string(path)
+            .map_err(|e| Error::Config(format!("read job cache {:?}: {e}", path)))?;
+        serde_json::from_str(data.as_str()).map_err(|e| Error::Decode(format!("parsing json: {e}")))
+    }
+
+    fn save(&self, path: &Path) -> Result<(), Error> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .map_err(|e| Error::Config(format!("write job cache {:?}: {e}", path)))?;
+        Ok(())
+    }
+}
+
+/// Hex sha256 of the file content, read by parts.
+pub async fn file_sha256(path: &Path) -> Result<String, Error> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; MIB as usize];
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+impl FingerprintApi {
+    pub async fn upload_audio(&self, file: &Path) -> Result<JobResponse, Error> {
+        self.upload_audio_with_progress(file, &|_| {}).await
+    }
+
+    /// Uploads the audio without loading it in memory, by resumable chunks when the api
+    /// has upload sessions, else streamed in one request. A job of the same content
+    /// found in the job cache is reused instead.
+    pub async fn upload_audio_with_progress(
+        &self,
+        file: &Path,
+        progress: &dyn Fn(UploadProgress),
+    ) -> Result<JobResponse, Error> {
+        let total = tokio::fs::metadata(file).await?.len();
+        let sha256 = file_sha256(file).await?;
+
+        if let Some(id) = self.cached_job(&sha256).await? {
+            progress(UploadProgress { sent: total, total });
+            return Ok(JobResponse { id, reused: true });
+        }
+
+        let session = match self.upload_options().resumable {
+            true => self.upload_session(&sha256, total).await?,
+            false => None,
+        };
+        let id = match session {
+            Some(session) => {
+                self.upload_chunks(file, session, &sha256, total, progress)
+                    .await?
+            }
+            None => {
+                self.with_retries(|| self.upload_stream(file, total, progress))
+                    .await?
+                    .id
+            }
+        };
+
+        if let Some(path) = &self.upload_options().job_cache {
+            let mut cache = JobCache::load(path)?;
+            cache.jobs.insert(
+                sha256,
+                CachedJob {
+                    api_url: self.api_url().to_string(),
+                    job_id: id.clone(),
+                },
+            );
+            cache.save(path)?;
+        }
+        Ok(JobResponse { id, reused: false })
+    }
+
+    /// Job of the same content uploaded to this api, if it is still known and not failed.
+    async fn cached_job(&self, sha256: &str) -> Result<Option<String>, Error> {
+        let Some(path) = &self.upload_options().job_cache else {
+            return Ok(None);
+        };
+        let Some(job) = JobCache::load(path)?.jobs.remove(sha256) else {
+            return Ok(None);
+        };
+        if job.api_url != self.api_url() {
+            return Ok(None);
+        }
+
+        match self.check_fingerprint_status(&job.job_id).await {
+            Ok(status) if status.status != "failed" => Ok(Some(job.job_id)),
+            Ok(_) | Err(Error::Api { status: 404, .. }) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Calls the request again on transient errors, with a doubling delay.
+    async fn with_retries<T, F, Fut>(&self, mut request: F) -> Result<T, Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        let options = self.upload_options();
+        let mut delay = options.retry_delay;
+        let mut retries = 0;
+        loop {
+            match request().await {
+                Err(e) if e.is_transient() && retries < options.retries => {
+                    retries += 1;
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Starts or continues the upload of the content, `None` if the api has no upload sessions:
+    /// any client error but an auth failure falls back to the upload in one request.
+    async fn upload_session(
+        &self,
+        sha256: &str,
+        size: u64,
+    ) -> Result<Option<UploadSession>, Error> {
+        self.with_retries(|| async {
+            let response = self
+                .http()
+                .post(self.endpoint("/v1/fingerprint/upload"))
+                .bearer_auth(self.auth_token()?)
+                .json(&json!({ "sha256": sha256, "size": size }))
+                .send()
+                .await?;
+
+            match response.status() {
+                reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN => {
+                    Err(status_error(response).await)
+                }
+                status if status.is_client_error() => Ok(None),
+                status if status.is_success() => Ok(Some(response.json().await?)),
+                _ => Err(status_error(response).await),
+            }
+        })
+        .await
+    }
+
+    /// Sends the chunks from the session offset, a failed chunk is resumed from the offset
+    /// the api has after a new session.
+    async fn upload_chunks(
+        &self,
+        path: &Path,
+        mut session: UploadSession,
+        sha256: &str,
+        total: u64,
+        progress: &dyn Fn(UploadProgress),
+    ) -> Result<String, Error> {
+        let options = self.upload_options();
+        let mut file = tokio::fs::File::open(path).await?;
+        let mut delay = options.retry_delay;
+        let mut retries = 0;
+
+        loop {
+            if let Some(id) = session.id.take() {
+                progress(UploadProgress { sent: total, total });
+                return Ok(id);
+            }
+            if session.offset >= total {
+                return Err(Error::Decode(format!(
+                    "upload {} is complete, but has no job id",
+                    session.upload_id
+                )));
+            }
+            progress(UploadProgress {
+                sent: session.offset,
+                total,
+            });
+
+            let len = options.chunk_size.min(total - session.offset);
+            let mut chunk = vec![0u8; len as usize];
+            file.seek(std::io::SeekFrom::Start(session.offset)).await?;
+            file.read_exact(&mut chunk).await?;
+
+            match self
+                .upload_chunk(&session.upload_id, session.offset, chunk, total)
+                .await
+            {
+                Ok(chunk) => {
+                    session.offset = chunk.offset;
+                    session.id = chunk.id;
+                    retries = 0;
+                    delay = options.retry_delay;
+                }
+                Err(e)
+                    if (e.is_transient() || matches!(e, Error::Api { status: 409, .. }))
+                        && retries < options.retries =>
+                {
+                    retries += 1;
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                    session = self.upload_session(sha256, total).await?.ok_or_else(|| {
+                        Error::NotFound(format!("upload {} not found", session.upload_id))
+                    })?;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    async fn upload_chunk(
+        &self,
+        upload_id: &str,
+        offset: u64,
+        chunk: Vec<u8>,
+        total: u64,
+    ) -> Result<ChunkResponse, Error> {
+        let end = offset + chunk.len() as u64 - 1;
+        let response = self
+            .http()
+            .put(self.endpoint(&format!("/v1/fingerprint/upload/{upload_id}")))
+            .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
+            .header(
+                reqwest::header::CONTENT_RANGE,
+                format!("bytes {offset}-{end}/{total}"),
+            )
+            .bearer_auth(self.auth_token()?)
+            .body(chunk)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(status_error(response).await);
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Streams the whole file in one request, for the apis without upload sessions.
+    async fn upload_stream(
+        &self,
+        path: &Path,
+        total: u64,
+        progress: &dyn Fn(UploadProgress),
+    ) -> Result<JobResponse, Error> {
+        let file = tokio::fs::File::open(path).await?;
+        let sent = Arc::new(AtomicU64::new(0));
+        let body_sent = sent.clone();
+        let body = futures::stream::try_unfold(file, move |mut file| {
+            let sent = body_sent.clone();
+            async move {
+                let mut buffer = vec![0u8; 256 * 1024];
+                let read = file.read(&mut buffer).await?;
+                if read == 0 {
+                    return Ok::<_, std::io::Error>(None);
+                }
+                buffer.truncate(read);
+                sent.fetch_add(read as u64, Ordering::Relaxed);
+                Ok(Some((buffer, file)))
+            }
+        });
+
+        // the request timeout is for small requests, at least 64 KiB/s is given to the upload
+        let timeout = self
+            .request_timeout()
+            .max(Duration::from_secs(total / 65536));
+        let request = self
+            .http()
+            .put(self.endpoint("/v1/fingerprint/create"))
+            .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
+            .header(reqwest::header::CONTENT_LENGTH, total)
+            .bearer_auth(self.auth_token()?)
+            .timeout(timeout)
+            .body(reqwest::Body::wrap_stream(body))
+            .send();
+        tokio::pin!(request);
+
+        let mut ticker = tokio::time::interval(Duration::from_millis(500));
+        let response = loop {
+            tokio::select! {
+                response = &mut request => break response?,
+                _ = ticker.tick() => progress(UploadProgress {
+                    sent: sent.load(Ordering::Relaxed),
+                    total,
+                }),
+            }
+        };
+        progress(UploadProgress {
+            sent: sent.load(Ordering::Relaxed),
+            total,
+        });
+
+        if !response.status().is_success() {
+            return Err(status_error(response).await);
+        }
+
+        Ok(response.json().await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_file_sha256() {
+        let path = std::env::temp_dir().join(format!("mubert-sha-{}", std::process::id()));
+        std::fs::write(&path, b"abc").unwrap();
+        assert_eq!(
+            file_sha256(&path).await.unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,219 @@
+use crate::api::{FingerprintApi, MetadataRequest};
+use crate::arweave::ARWEAVE_URL;
+use crate::block::At;
+use crate::client::MubertClient;
+use crate::error::Error;
+use serde::Serialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Exit code of `verify-entity` when the metadata does not match.
+pub const VERIFY_FAILED_EXIT_CODE: i32 = 3;
+
+/// What does not match between the entity and its metadata.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(tag = "problem", rename_all = "snake_case")]
+pub enum Problem {
+    /// The url could not be downloaded.
+    Unreachable { url: String, error: String },
+    /// The metadata document is not a `MetadataRequest`.
+    BadMetadata { error: String },
+    /// The fingerprint of the metadata is not the one of the local audio.
+    FingerprintMismatch { metadata: String, local: String },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::Unreachable { url, error } => write!(f, "{url} unreachable: {error}"),
+            Problem::BadMetadata { error } => write!(f, "metadata is not valid: {error}"),
+            Problem::FingerprintMismatch { metadata, local } => write!(
+                f,
+                "fingerprint {metadata} of the metadata does not match {local} of the audio"
+            ),
+        }
+    }
+}
+
+/// Result of the checks of the metadata url of an entity.
+#[derive(Serialize, Debug)]
+pub struct MetadataVerification {
+    pub metadata_url: String,
+    /// The url downloaded, the arweave one is read from the gateway if given.
+    pub fetched_url: String,
+    pub metadata: Option<MetadataRequest>,
+    /// Fingerprint url of the local audio, if one was given.
+    pub local_fingerprint: Option<String>,
+    pub problems: Vec<Problem>,
+}
+
+impl MetadataVerification {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Url to download, `https://arweave.net/<tx hash>` is read from the gateway if given.
+pub fn fetch_url(metadata_url: &str, gateway_url: Option<&str>) -> String {
+    match (metadata_url.strip_prefix(ARWEAVE_URL), gateway_url) {
+        (Some(tx_hash), Some(gateway_url)) => {
+            format!("{}/{tx_hash}", gateway_url.trim_end_matches('/'))
+        }
+        _ => metadata_url.to_string(),
+    }
+}
+
+async fn download(api: &FingerprintApi, url: &str) -> Result<Vec<u8>, Problem> {
+    let unreachable = |error: String| Problem::Unreachable {
+        url: url.to_string(),
+        error,
+    };
+    let response = api
+        .http()
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| unreachable(e.to_string()))?;
+    let status = response.status();
+    let body = response
+        .bytes()
+        .await
+        .map_err(|e| unreachable(e.to_string()))?;
+    if !status.is_success() {
+        return Err(unreachable(format!(
+            "{status}: {}",
+            String::from_utf8_lossy(&body)
+        )));
+    }
+    Ok(body.to_vec())
+}
+
+/// Downloads the metadata and checks it is a `MetadataRequest`, with the fingerprint of
+/// `file` if given. The fingerprint urls match if they are the same or serve the same document.
+///
+/// Unreachable urls and mismatches are listed in the result, the errors are the ones of
+/// fingerprinting the local audio.
+pub async fn verify_metadata(
+    api: &FingerprintApi,
+    metadata_url: &str,
+    gateway_url: Option<&str>,
+    file: Option<&Path>,
+) -> Result<MetadataVerification, Error> {
+    let fetched_url = fetch_url(metadata_url, gateway_url);
+    let mut verification = MetadataVerification {
+        metadata_url: metadata_url.to_string(),
+        fetched_url: fetched_url.clone(),
+        metadata: None,
+        local_fingerprint: None,
+        problems: Vec::new(),
+    };
+
+    match download(api, &fetched_url).await {
+        Ok(body) => match serde_json::from_slice::<MetadataRequest>(&body) {
+            Ok(metadata) => verification.metadata = Some(metadata),
+            Err(e) => verification.problems.push(Problem::BadMetadata {
+                error: e.to_string(),
+            }),
+        },
+        Err(problem) => verification.problems.push(problem),
+    }
+
+    let Some(file) = file else {
+        return Ok(verification);
+    };
+    let local = api.fingerprint(file).await?;
+    verification.local_fingerprint = Some(local.clone());
+
+    let Some(metadata) = &verification.metadata else {
+        return Ok(verification);
+    };
+    if metadata.fingerprint == local {
+        return Ok(verification);
+    }
+    let fingerprints = (
+        download(api, &metadata.fingerprint).await,
+        download(api, &local).await,
+    );
+    match fingerprints {
+        (Ok(metadata_doc), Ok(local_doc)) if metadata_doc == local_doc => {}
+        (Err(problem), _) | (_, Err(problem)) => verification.problems.push(problem),
+        _ => verification.problems.push(Problem::FingerprintMismatch {
+            metadata: metadata.fingerprint.clone(),
+            local,
+        }),
+    }
+    Ok(verification)
+}
+
+/// Verification of an entity, with the metadata url of its on chain details.
+#[derive(Serialize, Debug)]
+pub struct EntityVerification {
+    pub entity_id: u32,
+    #[serde(flatten)]
+    pub metadata: MetadataVerification,
+}
+
+impl MubertClient {
+    /// Checks the metadata the entity points to, see `verify_metadata`.
+    pub async fn verify_entity(
+        &self,
+        entity_id: u32,
+        gateway_url: Option<&str>,
+        file: Option<&Path>,
+        at: &Option<At>,
+    ) -> Result<EntityVerification, Error> {
+        let entity = self.get_entity(entity_id, at).await?.details;
+        let metadata_url = String::from_utf8_lossy(&entity.metadata.url.0).to_string();
+        Ok(EntityVerification {
+            entity_id,
+            metadata: verify_metadata(self.fingerprint_api(), &metadata_url, gateway_url, file)
+                .await?,
+        })
+    }
+}
+
+pub async fn verify_entity(
+    client: &MubertClient,
+    entity_id: u32,
+    gateway_url: &Option<String>,
+    file: &Option<PathBuf>,
+    at: &Option<At>,
+) -> Result<(), Error> {
+    let verification = client
+        .verify_entity(entity_id, gateway_url.as_deref(), file.as_deref(), at)
+        .await?;
+    println!("{}", serde_json::to_string(&verification)?);
+
+    if verification.metadata.is_ok() {
+        return Ok(());
+    }
+    for problem in &verification.metadata.problems {
+        eprintln!("{problem}");
+    }
+    Err(Error::Verification(format!(
+        "entity {entity_id}: {} problems found",
+        verification.metadata.problems.len()
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fetch_url() {
+        let url = "https://arweave.net/abc";
+        assert_eq!(fetch_url(url, None), url);
+        assert_eq!(
+            fetch_url(url, Some("http://127.0.0.1:1984/")),
+            "http://127.0.0.1:1984/abc"
+        );
+        assert_eq!(
+            fetch_url(
+                "https://fingerprint.mubert.xyz/metadata/1",
+                Some("http://gw")
+            ),
+            "https://fingerprint.mubert.xyz/metadata/1"
+        );
+    }
+}
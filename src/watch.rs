@@ -0,0 +1,326 @@
+use crate::block::{extrinsic_signers, Block, BlockInfo};
+use crate::client::MubertClient;
+use crate::error::Error;
+use crate::ip_onchain_runtime;
+use crate::ip_onchain_runtime::runtime_types::pallet_arweave::pallet::Event as ArweaveEvent;
+use crate::ip_onchain_runtime::runtime_types::pallet_ip_onchain::pallet::Event as IPOnchainEvent;
+use serde::Serialize;
+use subxt::events::{EventDetails, Phase};
+use subxt::utils::AccountId32;
+use subxt::PolkadotConfig;
+
+#[derive(clap::Args, Clone, Default)]
+pub struct WatchFilter {
+    #[arg(short = 'a', long, help = "only events of this authority")]
+    pub authority_id: Option<u32>,
+    #[arg(short = 'e', long, help = "only events of this entity")]
+    pub entity_id: Option<u32>,
+    #[arg(long, help = "only events signed by or related to this account")]
+    pub account_id: Option<AccountId32>,
+}
+
+#[derive(Serialize, Clone)]
+struct WatchEvent {
+    #[serde(flatten)]
+    block: BlockInfo,
+    event_index: u32,
+    extrinsic_index: Option<u32>,
+    signer: Option<AccountId32>,
+    pallet: String,
+    event: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    authority_id: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    author_id: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    entity_id: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    task_id: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    account_id: Option<AccountId32>,
+}
+
+impl WatchEvent {
+    fn new(block: &Block, event: &EventDetails<PolkadotConfig>) -> Self {
+        WatchEvent {
+            block: block.into(),
+            event_index: event.index(),
+            extrinsic_index: match event.phase() {
+                Phase::ApplyExtrinsic(index) => Some(index),
+                _ => None,
+            },
+            signer: None,
+            pallet: event.pallet_name().to_string(),
+            event: event.variant_name().to_string(),
+            authority_id: None,
+            author_id: None,
+            entity_id: None,
+            request_id: None,
+            task_id: None,
+            account_id: None,
+        }
+    }
+
+    fn text(&self) -> String {
+        let mut line = format!(
+            "#{} {}.{}",
+            self.block.block_number, self.pallet, self.event
+        );
+        let ids = [
+            ("authority_id", self.authority_id.map(|id| id.to_string())),
+            ("author_id", self.author_id.map(|id| id.to_string())),
+            ("entity_id", self.entity_id.map(|id| id.to_string())),
+            ("request_id", self.request_id.map(|id| id.to_string())),
+            ("task_id", self.task_id.map(|id| id.to_string())),
+            (
+                "account_id",
+                self.account_id.as_ref().map(|a| a.to_string()),
+            ),
+            ("signer", self.signer.as_ref().map(|a| a.to_string())),
+        ];
+        for (name, value) in ids {
+            if let Some(value) = value {
+                line.push_str(&format!(" {name}={value}"));
+            }
+        }
+        line
+    }
+}
+
+impl WatchFilter {
+    fn matches(&self, event: &WatchEvent) -> bool {
+        if self
+            .authority_id
+            .is_some_and(|id| event.authority_id != Some(id))
+        {
+            return false;
+        }
+        if self.entity_id.is_some_and(|id| event.entity_id != Some(id)) {
+            return false;
+        }
+        if let Some(account_id) = &self.account_id {
+            if event.signer.as_ref() != Some(account_id)
+                && event.account_id.as_ref() != Some(account_id)
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Decodes the ip_onchain and arweave events, other pallets are skipped.
+fn decode_event(
+    block: &Block,
+    event: &EventDetails<PolkadotConfig>,
+) -> Result<Option<WatchEvent>, Error> {
+    if event.pallet_name() != "IPOnchain" && event.pallet_name() != "Arweave" {
+        return Ok(None);
+    }
+
+    let mut watch_event = WatchEvent::new(block, event);
+    match event.as_root_event::<ip_onchain_runtime::Event>()? {
+        ip_onchain_runtime::Event::IPOnchain(e) => match e {
+            IPOnchainEvent::AuthorAdded { author_id }
+            | IPOnchainEvent::AuthorEdited { author_id } => {
+                watch_event.author_id = Some(author_id);
+            }
+            IPOnchainEvent::AuthorityAdded { authority_id }
+            | IPOnchainEvent::AuthorityEdited { authority_id } => {
+                watch_event.authority_id = Some(authority_id);
+            }
+            IPOnchainEvent::AuthoritiesAccessAdded {
+                authority_id,
+                account_id,
+            }
+            | IPOnchainEvent::AuthoritiesAccessChanged {
+                authority_id,
+                account_id,
+            } => {
+                watch_event.authority_id = Some(authority_id);
+                watch_event.account_id = Some(account_id);
+            }
+            IPOnchainEvent::EntityAdded { entity_id }
+            | IPOnchainEvent::EntityEdited { entity_id }
+            | IPOnchainEvent::EntityWraped { entity_id }
+            | IPOnchainEvent::EntityMovedForeign { entity_id } => {
+                watch_event.entity_id = Some(entity_id);
+            }
+            IPOnchainEvent::ForeignRequestsAdded { request_id }
+            | IPOnchainEvent::ForeignRequestsDone { request_id } => {
+                watch_event.request_id = Some(request_id);
+            }
+        },
+        ip_onchain_runtime::Event::Arweave(e) => match e {
+            ArweaveEvent::TaskAdded { task_id }
+            | ArweaveEvent::TaskChanged { task_id }
+            | ArweaveEvent::TaskCleared { task_id } => {
+                watch_event.task_id = Some(task_id);
+            }
+        },
+        _ => return Ok(None),
+    }
+    Ok(Some(watch_event))
+}
+
+/// Fills the ids the event itself does not carry from the state at the event block.
+async fn add_related(block: &Block, watch_event: &mut WatchEvent) -> Result<(), Error> {
+    if let Some(entity_id) = watch_event.entity_id {
+        let query = ip_onchain_runtime::storage()
+            .ip_onchain()
+            .entities(entity_id);
+        if let Some(entity) = block.storage().fetch(&query).await? {
+            watch_event.authority_id = Some(entity.owner);
+        }
+    }
+
+    if let Some(request_id) = watch_event.request_id {
+        let query = ip_onchain_runtime::storage()
+            .ip_onchain()
+            .foreigns_requests(request_id);
+        if let Some(request) = block.storage().fetch(&query).await? {
+            watch_event.authority_id = Some(request.here_authority_id);
+            watch_event.entity_id = Some(request.entity_id);
+            watch_event.account_id = Some(request.who);
+        }
+    }
+
+    if let Some(task_id) = watch_event.task_id {
+        let query = ip_onchain_runtime::storage().arweave().tasks(task_id);
+        if let Some(task) = block.storage().fetch(&query).await? {
+            watch_event.account_id = Some(task.worker_address);
+        }
+    }
+    Ok(())
+}
+
+/// Events of the block found by the watch, with their signers. An event failing to
+/// decode is reported and skipped, the others are kept.
+async fn block_events(block: &Block) -> Result<Vec<WatchEvent>, Error> {
+    let mut found = Vec::new();
+    for event in block.events().await?.iter() {
+        let decoded = event
+            .map_err(Error::from)
+            .and_then(|event| decode_event(block, &event));
+        match decoded {
+            Ok(Some(mut watch_event)) => {
+                add_related(block, &mut watch_event).await?;
+                found.push(watch_event);
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("block #{}: skipped an event: {e}", block.number()),
+        }
+    }
+
+    if found.iter().any(|e| e.extrinsic_index.is_some()) {
+        let signers = extrinsic_signers(block).await?;
+        for watch_event in found.iter_mut() {
+            watch_event.signer = watch_event
+                .extrinsic_index
+                .and_then(|index| signers.get(&index).cloned());
+        }
+    }
+    Ok(found)
+}
+
+/// Prints the events of every finalized block until the subscription is closed,
+/// a block failing to be read is reported on stderr and skipped.
+pub async fn watch(client: &MubertClient, filter: &WatchFilter, json: bool) -> Result<(), Error> {
+    let mut blocks = client
+        .api()
+        .blocks()
+        .subscribe_finalized()
+        .await
+        .map_err(|e| Error::Rpc(format!("subscribe finalized blocks: {e}")))?;
+
+    while let Some(block) = blocks.next().await {
+        let block = match block {
+            Ok(block) => block,
+            Err(e) => {
+                eprintln!("skipped a finalized block: {e}");
+                continue;
+            }
+        };
+        let found = match block_events(&block).await {
+            Ok(found) => found,
+            Err(e) => {
+                eprintln!("block #{}: skipped: {e}", block.number());
+                continue;
+            }
+        };
+
+        for watch_event in found.iter().filter(|e| filter.matches(e)) {
+            match json {
+                true => println!("{}", serde_json::to_string(watch_event)?),
+                false => println!("{}", watch_event.text()),
+            }
+        }
+    }
+
+    Err(Error::Config("finalized blocks subscription closed".into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use subxt::utils::H256;
+
+    fn entity_event(authority_id: u32, entity_id: u32, signer: AccountId32) -> WatchEvent {
+        WatchEvent {
+            block: BlockInfo {
+                block_number: 7,
+                block_hash: H256::zero(),
+            },
+            event_index: 2,
+            extrinsic_index: Some(1),
+            signer: Some(signer),
+            pallet: "IPOnchain".to_string(),
+            event: "EntityAdded".to_string(),
+            authority_id: Some(authority_id),
+            author_id: None,
+            entity_id: Some(entity_id),
+            request_id: None,
+            task_id: None,
+            account_id: None,
+        }
+    }
+
+    #[test]
+    fn test_filter() {
+        let alice = AccountId32([1; 32]);
+        let bob = AccountId32([2; 32]);
+        let event = entity_event(1, 5, alice.clone());
+
+        assert!(WatchFilter::default().matches(&event));
+        let filter = WatchFilter {
+            authority_id: Some(1),
+            entity_id: Some(5),
+            account_id: Some(alice),
+        };
+        assert!(filter.matches(&event));
+        let filter = WatchFilter {
+            authority_id: Some(2),
+            ..Default::default()
+        };
+        assert!(!filter.matches(&event));
+        let filter = WatchFilter {
+            account_id: Some(bob),
+            ..Default::default()
+        };
+        assert!(!filter.matches(&event));
+    }
+
+    #[test]
+    fn test_text() {
+        let event = entity_event(1, 5, AccountId32([1; 32]));
+        let text = event.text();
+        assert!(text.starts_with("#7 IPOnchain.EntityAdded authority_id=1 entity_id=5 signer="));
+
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["block_number"], 7);
+        assert_eq!(json["entity_id"], 5);
+        assert!(json.get("task_id").is_none());
+    }
+}
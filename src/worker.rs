@@ -0,0 +1,543 @@
+use crate::api::status_error;
+use crate::arweave::TaskTransition;
+use crate::client::MubertClient;
+use crate::error::Error;
+use crate::ip_onchain_runtime;
+use crate::ip_onchain_runtime::arweave::storage::types::tasks::Tasks;
+use crate::ip_onchain_runtime::runtime_types::pallet_arweave::types::TaskState;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use subxt::ext::codec::Encode;
+use subxt::utils::AccountId32;
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct WorkerArgs {
+    #[arg(
+        long,
+        env = "MUBERT_ARWEAVE_GATEWAY",
+        help = "arweave upload gateway, taking the raw data on POST /tx"
+    )]
+    pub gateway_url: String,
+    #[arg(
+        long,
+        env = "MUBERT_ARWEAVE_GATEWAY_TOKEN",
+        help = "bearer token of the gateway"
+    )]
+    pub gateway_token: Option<String>,
+    #[arg(
+        long,
+        help = "local state of the worker, <data dir>/mubert-cli/worker/<account>.json by default"
+    )]
+    pub state_file: Option<PathBuf>,
+    #[arg(
+        long,
+        default_value_t = 6,
+        help = "seconds between two scans of the tasks"
+    )]
+    pub poll_interval: u64,
+    #[arg(long, help = "work on the current tasks once and exit")]
+    pub once: bool,
+}
+
+/// Status of an upload on the gateway.
+#[derive(Debug, PartialEq)]
+pub enum GatewayStatus {
+    /// Mined, the data is served by arweave.
+    Confirmed,
+    /// Received, not mined yet.
+    Pending,
+    /// Unknown to the gateway, dropped or never received.
+    NotFound,
+}
+
+#[derive(Deserialize)]
+struct UploadResponse {
+    id: String,
+}
+
+/// Arweave compatible gateway the worker uploads to: the raw data goes to `POST /tx`
+/// answering `{"id": <arweave tx id>}`, and `GET /tx/{id}/status` answers 202 until mined.
+pub struct ArweaveGateway {
+    http: reqwest::Client,
+    url: String,
+    auth_token: Option<String>,
+}
+
+impl ArweaveGateway {
+    pub fn new(url: &str, auth_token: Option<String>) -> Result<Self, Error> {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(120))
+            .build()?;
+        Ok(ArweaveGateway {
+            http,
+            url: url.trim_end_matches('/').to_string(),
+            auth_token,
+        })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let request = self.http.request(method, format!("{}{path}", self.url));
+        match &self.auth_token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    /// Uploads the data with the worker signature of it, returns the arweave tx id.
+    pub async fn upload(&self, data: &[u8], signed_data: &[u8]) -> Result<String, Error> {
+        let response = self
+            .request(reqwest::Method::POST, "/tx")
+            .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
+            .header("x-signature", format!("0x{}", hex::encode(signed_data)))
+            .body(data.to_vec())
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(status_error(response).await);
+        }
+
+        Ok(response.json::<UploadResponse>().await?.id)
+    }
+
+    pub async fn status(&self, tx_id: &str) -> Result<GatewayStatus, Error> {
+        let response = self
+            .request(reqwest::Method::GET, &format!("/tx/{tx_id}/status"))
+            .send()
+            .await?;
+
+        match response.status().as_u16() {
+            200 => Ok(GatewayStatus::Confirmed),
+            202 => Ok(GatewayStatus::Pending),
+            404 => Ok(GatewayStatus::NotFound),
+            _ => Err(status_error(response).await),
+        }
+    }
+}
+
+/// Counters of the worker, for one run and in total in the state file.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct WorkerMetrics {
+    pub rounds: u64,
+    pub signed: u64,
+    pub uploaded: u64,
+    pub uploaded_bytes: u64,
+    pub validated: u64,
+    pub failed: u64,
+}
+
+impl fmt::Display for WorkerMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} rounds, {} signed, {} uploaded ({} bytes), {} validated, {} failed",
+            self.rounds,
+            self.signed,
+            self.uploaded,
+            self.uploaded_bytes,
+            self.validated,
+            self.failed
+        )
+    }
+}
+
+/// Work done on a task not advanced on chain yet.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct WorkerTask {
+    /// Hex of the scale encoded signature of the task data, signed once.
+    pub signed_data: Option<String>,
+    /// Arweave tx id of the uploaded data, so a restart does not upload it again.
+    pub tx_id: Option<String>,
+}
+
+/// Local state of the worker, written after every step so a killed worker
+/// continues where it stopped.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct WorkerState {
+    #[serde(default)]
+    pub tasks: BTreeMap<u64, WorkerTask>,
+    #[serde(default)]
+    pub totals: WorkerMetrics,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl WorkerState {
+    pub fn default_path(account: &AccountId32) -> Result<PathBuf, Error> {
+        Ok(dirs::data_local_dir()
+            .ok_or_else(|| Error::Config("no data dir, set --state-file".into()))?
+            .join("mubert-cli")
+            .join("worker")
+            .join(format!("{account}.json")))
+    }
+
+    /// The state of the path, an empty one if there is none yet.
+    pub fn open(path: PathBuf) -> Result<Self, Error> {
+        let mut state: WorkerState = match path.exists() {
+            true => {
+                let data = std::fs::read_to_string(&path)
+                    .map_err(|e| Error::Config(format!("read worker state {:?}: {e}", path)))?;
+                serde_json::from_str(data.as_str())
+                    .map_err(|e| Error::Decode(format!("parsing worker state {:?}: {e}", path)))?
+            }
+            false => WorkerState::default(),
+        };
+        state.path = path;
+        Ok(state)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes a temporary file renamed over the state, a crash leaves the old or the new one.
+    pub fn save(&self) -> Result<(), Error> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_string_pretty(self)?)
+            .map_err(|e| Error::Config(format!("write worker state {:?}: {e}", tmp)))?;
+        std::fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+/// What the worker did, for the cli to print.
+#[derive(Debug)]
+pub enum WorkerEvent {
+    Transition(TaskTransition),
+    Uploaded {
+        task_id: u64,
+        tx_id: String,
+        bytes: usize,
+    },
+    Failed {
+        task_id: u64,
+        error: Error,
+    },
+}
+
+impl fmt::Display for WorkerEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WorkerEvent::Transition(transition) => write!(f, "{transition}"),
+            WorkerEvent::Uploaded {
+                task_id,
+                tx_id,
+                bytes,
+            } => write!(
+                f,
+                "arweave task {task_id}: uploaded {bytes} bytes, tx {tx_id}"
+            ),
+            WorkerEvent::Failed { task_id, error } => {
+                write!(
+                    f,
+                    "arweave task {task_id}: failed, retried next round: {error}"
+                )
+            }
+        }
+    }
+}
+
+/// Moves the tasks of the signer account through the `pallet_arweave` protocol:
+/// a `Sign` task gets the signature of its data and goes to `Upload`, an `Upload` task
+/// is uploaded to the gateway and goes to `Validate` with the tx hash once mined.
+pub struct ArweaveWorker<'a> {
+    client: &'a MubertClient,
+    gateway: ArweaveGateway,
+    state: WorkerState,
+    account: AccountId32,
+    metrics: WorkerMetrics,
+}
+
+impl<'a> ArweaveWorker<'a> {
+    pub fn new(
+        client: &'a MubertClient,
+        gateway: ArweaveGateway,
+        state: WorkerState,
+    ) -> Result<Self, Error> {
+        Ok(ArweaveWorker {
+            account: client.signer()?.account_id(),
+            client,
+            gateway,
+            state,
+            metrics: WorkerMetrics::default(),
+        })
+    }
+
+    pub fn account(&self) -> &AccountId32 {
+        &self.account
+    }
+
+    /// Counters of this run.
+    pub fn metrics(&self) -> &WorkerMetrics {
+        &self.metrics
+    }
+
+    pub fn state(&self) -> &WorkerState {
+        &self.state
+    }
+
+    fn count(&mut self, add: impl Fn(&mut WorkerMetrics)) {
+        add(&mut self.metrics);
+        add(&mut self.state.totals);
+    }
+
+    /// Works once on every task of the worker. A failed task is reported and tried again
+    /// on the next round, an error reading the tasks is returned.
+    pub async fn round(&mut self, report: &dyn Fn(WorkerEvent)) -> Result<(), Error> {
+        let tasks = self.assigned_tasks().await?;
+        self.count(|m| m.rounds += 1);
+
+        // tasks validated or cleared by an other run are done
+        self.state
+            .tasks
+            .retain(|task_id, _| tasks.iter().any(|task| task.task_id == *task_id));
+
+        for task in tasks {
+            let task_id = task.task_id;
+            if let Err(error) = self.work_on(task, report).await {
+                self.count(|m| m.failed += 1);
+                report(WorkerEvent::Failed { task_id, error });
+            }
+        }
+        self.state.save()
+    }
+
+    /// Tasks of the account still to sign or to upload, by id.
+    async fn assigned_tasks(&self) -> Result<Vec<Tasks>, Error> {
+        let query = ip_onchain_runtime::storage().arweave().tasks_iter();
+        let mut results = self
+            .client
+            .api()
+            .storage()
+            .at_latest()
+            .await?
+            .iter(query)
+            .await?;
+
+        let mut tasks = Vec::new();
+        while let Some(kv) = results.next().await {
+            let task = kv?.value;
+            if task.worker_address == self.account
+                && matches!(task.state, TaskState::Sign | TaskState::Upload)
+            {
+                tasks.push(task);
+            }
+        }
+        tasks.sort_by_key(|task| task.task_id);
+        Ok(tasks)
+    }
+
+    async fn work_on(&mut self, task: Tasks, report: &dyn Fn(WorkerEvent)) -> Result<(), Error> {
+        let task_id = task.task_id;
+        let signed_data = self.signed_data(&task).await?;
+
+        match task.state {
+            TaskState::Sign => {
+                // signed before the update to Upload failed, only the update is sent again
+                if self.signed_on_chain(task_id).await?.as_ref() != Some(&signed_data) {
+                    self.client
+                        .sign_arweave_task(task_id, signed_data, None)
+                        .await?
+                        .into_result()?;
+                    self.count(|m| m.signed += 1);
+                }
+                let transition = self
+                    .client
+                    .update_arweave_task(task_id, TaskState::Upload)
+                    .await?
+                    .into_result()?;
+                report(WorkerEvent::Transition(transition));
+            }
+            TaskState::Upload => {
+                let tx_id = match self.tx_id(task_id) {
+                    Some(tx_id) => tx_id,
+                    None => {
+                        let tx_id = self.gateway.upload(&task.data.0, &signed_data).await?;
+                        self.task(task_id).tx_id = Some(tx_id.clone());
+                        self.state.save()?;
+                        let bytes = task.data.0.len();
+                        self.count(|m| {
+                            m.uploaded += 1;
+                            m.uploaded_bytes += bytes as u64
+                        });
+                        report(WorkerEvent::Uploaded {
+                            task_id,
+                            tx_id: tx_id.clone(),
+                            bytes,
+                        });
+                        tx_id
+                    }
+                };
+
+                match self.gateway.status(&tx_id).await? {
+                    GatewayStatus::Pending => return Ok(()),
+                    GatewayStatus::NotFound => {
+                        // dropped by the gateway, uploaded again next round
+                        self.task(task_id).tx_id = None;
+                        return self.state.save();
+                    }
+                    GatewayStatus::Confirmed => {}
+                }
+
+                // signed with the tx id before the update to Validate failed, only the
+                // update is sent again
+                let signed = task.tx_hash.as_ref().map(|tx_hash| &tx_hash.0[..])
+                    == Some(tx_id.as_bytes())
+                    && self.signed_on_chain(task_id).await?.as_ref() == Some(&signed_data);
+                if !signed {
+                    self.client
+                        .sign_arweave_task(task_id, signed_data, Some(tx_id))
+                        .await?
+                        .into_result()?;
+                }
+                let transition = self
+                    .client
+                    .update_arweave_task(task_id, TaskState::Validate)
+                    .await?
+                    .into_result()?;
+                self.count(|m| m.validated += 1);
+                self.state.tasks.remove(&task_id);
+                self.state.save()?;
+                report(WorkerEvent::Transition(transition));
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn task(&mut self, task_id: u64) -> &mut WorkerTask {
+        self.state.tasks.entry(task_id).or_default()
+    }
+
+    fn tx_id(&self, task_id: u64) -> Option<String> {
+        self.state.tasks.get(&task_id)?.tx_id.clone()
+    }
+
+    /// Signed data of the task stored on chain by `sign_task_data`.
+    async fn signed_on_chain(&self, task_id: u64) -> Result<Option<Vec<u8>>, Error> {
+        let query = ip_onchain_runtime::storage()
+            .arweave()
+            .tasks_signed_data(task_id);
+        let signed_data = self
+            .client
+            .api()
+            .storage()
+            .at_latest()
+            .await?
+            .fetch(&query)
+            .await?;
+        Ok(signed_data.map(|data| data.0))
+    }
+
+    /// Signature of the task data, the saved one if the task was signed before,
+    /// or the one on chain if the state file lost it.
+    async fn signed_data(&mut self, task: &Tasks) -> Result<Vec<u8>, Error> {
+        let saved = self.state.tasks.get(&task.task_id);
+        if let Some(signed_data) = saved.and_then(|t| t.signed_data.as_ref()) {
+            return Ok(hex::decode(signed_data)?);
+        }
+        let signed_data = match self.signed_on_chain(task.task_id).await? {
+            Some(signed_data) => signed_data,
+            None => self.client.signer()?.sign(&task.data.0)?.encode(),
+        };
+        self.task(task.task_id).signed_data = Some(hex::encode(&signed_data));
+        self.state.save()?;
+        Ok(signed_data)
+    }
+}
+
+/// Runs the worker until Ctrl-C, or for one round with `once`, then prints its metrics.
+pub async fn run(client: &MubertClient, args: &WorkerArgs) -> Result<(), Error> {
+    let account = client.signer()?.account_id();
+    let path = match &args.state_file {
+        Some(path) => path.clone(),
+        None => WorkerState::default_path(&account)?,
+    };
+    let gateway = ArweaveGateway::new(&args.gateway_url, args.gateway_token.clone())?;
+    let mut worker = ArweaveWorker::new(client, gateway, WorkerState::open(path)?)?;
+    println!(
+        "arweave worker {account} uploading to {}, state in {:?}",
+        worker.gateway.url(),
+        worker.state().path()
+    );
+
+    let report = |event: WorkerEvent| println!("{event}");
+    let interval = Duration::from_secs(args.poll_interval.max(1));
+    let result = loop {
+        let round = tokio::select! {
+            biased;
+            _ = tokio::signal::ctrl_c() => break Ok(()),
+            round = worker.round(&report) => round,
+        };
+        match round {
+            Err(e) if e.is_transient() => eprintln!("reading the tasks failed, retried: {e}"),
+            Err(e) => break Err(e),
+            Ok(()) => {}
+        }
+        if args.once {
+            break Ok(());
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(interval) => {}
+            _ = tokio::signal::ctrl_c() => break Ok(()),
+        }
+    };
+
+    println!("worker metrics: {}", worker.metrics());
+    println!("worker totals: {}", worker.state().totals);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_worker_state() {
+        let dir = std::env::temp_dir().join(format!("mubert-worker-{}", std::process::id()));
+        let path = dir.join("worker.json");
+
+        let mut state = WorkerState::open(path.clone()).unwrap();
+        assert_eq!(state.tasks.len(), 0);
+        state.tasks.insert(
+            4,
+            WorkerTask {
+                signed_data: Some("01ab".into()),
+                tx_id: Some("tx-4".into()),
+            },
+        );
+        state.totals.uploaded = 1;
+        state.save().unwrap();
+        assert!(!path.with_extension("json.tmp").exists());
+
+        assert_eq!(WorkerState::open(path).unwrap(), state);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_metrics() {
+        let metrics = WorkerMetrics {
+            rounds: 3,
+            signed: 2,
+            uploaded: 1,
+            uploaded_bytes: 120,
+            validated: 1,
+            failed: 0,
+        };
+        assert_eq!(
+            metrics.to_string(),
+            "3 rounds, 2 signed, 1 uploaded (120 bytes), 1 validated, 0 failed"
+        );
+    }
+}
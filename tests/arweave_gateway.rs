@@ -0,0 +1,91 @@
+//! Gateway part of `worker run` and `verify-entity` against the local mock arweave gateway.
+
+use mubert_cli::api::{FingerprintApi, MetadataRequest};
+use mubert_cli::error::Error;
+use mubert_cli::mock_arweave::MockArweave;
+use mubert_cli::verify::{verify_metadata, Problem};
+use mubert_cli::worker::{ArweaveGateway, GatewayStatus};
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_upload_until_confirmed() {
+    let mock = MockArweave::start("127.0.0.1:0", 2).await.unwrap();
+    let gateway = ArweaveGateway::new(&mock.url(), None).unwrap();
+
+    let data = br#"{"name":"track"}"#;
+    let id = gateway.upload(data, &[1, 2, 3]).await.unwrap();
+    assert_eq!(id.len(), 43);
+    assert_eq!(gateway.upload(data, &[1, 2, 3]).await.unwrap(), id);
+
+    assert_eq!(gateway.status(&id).await.unwrap(), GatewayStatus::Pending);
+    assert_eq!(gateway.status(&id).await.unwrap(), GatewayStatus::Pending);
+    assert_eq!(gateway.status(&id).await.unwrap(), GatewayStatus::Confirmed);
+
+    let uploads = mock.uploads();
+    assert_eq!(uploads.len(), 1);
+    assert_eq!(uploads[0].data, data);
+    assert_eq!(uploads[0].signature.as_deref(), Some("0x010203"));
+
+    let served = reqwest::get(format!("{}/{id}", mock.url()))
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(served.as_bytes(), data);
+}
+
+#[tokio::test]
+async fn test_dropped_upload() {
+    let mock = MockArweave::start("127.0.0.1:0", 0).await.unwrap();
+    let gateway = ArweaveGateway::new(&mock.url(), None).unwrap();
+
+    let id = gateway.upload(b"data", &[]).await.unwrap();
+    mock.forget(&id);
+    assert_eq!(gateway.status(&id).await.unwrap(), GatewayStatus::NotFound);
+}
+
+#[tokio::test]
+async fn test_gateway_down() {
+    let gateway = ArweaveGateway::new("http://127.0.0.1:1", None).unwrap();
+    let result = gateway.upload(b"data", &[]).await;
+    assert!(matches!(result, Err(ref e @ Error::Http(_)) if e.is_transient()));
+}
+
+#[tokio::test]
+async fn test_verify_arweave_metadata() {
+    let mock = MockArweave::start("127.0.0.1:0", 0).await.unwrap();
+    let gateway = ArweaveGateway::new(&mock.url(), None).unwrap();
+    let api = FingerprintApi::new("http://127.0.0.1:1", None, Duration::from_secs(5)).unwrap();
+
+    let metadata = MetadataRequest {
+        title: "track".into(),
+        bpm: 120,
+        key: 1,
+        scale: 0,
+        instrument: 2,
+        fingerprint: "https://fingerprint.mubert.xyz/fingerprint/1".into(),
+    };
+    let id = gateway
+        .upload(&serde_json::to_vec(&metadata).unwrap(), &[])
+        .await
+        .unwrap();
+    let url = format!("https://arweave.net/{id}");
+
+    let verification = verify_metadata(&api, &url, Some(&mock.url()), None)
+        .await
+        .unwrap();
+    assert!(verification.is_ok());
+    assert_eq!(verification.fetched_url, format!("{}/{id}", mock.url()));
+    assert_eq!(verification.metadata, Some(metadata));
+
+    let id = gateway.upload(b"not json", &[]).await.unwrap();
+    let url = format!("https://arweave.net/{id}");
+    let verification = verify_metadata(&api, &url, Some(&mock.url()), None)
+        .await
+        .unwrap();
+    assert!(matches!(
+        &verification.problems[..],
+        [Problem::BadMetadata { .. }]
+    ));
+}
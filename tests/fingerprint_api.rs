@@ -0,0 +1,407 @@
+//! Off chain part of `upload-ip` against the local mock fingerprint api.
+
+use mubert_cli::api::FingerprintApi;
+use mubert_cli::error::Error;
+use mubert_cli::mock_fingerprint::{MockFailure, MockFingerprint, MockScript};
+use mubert_cli::poll::{PollOptions, Waiting};
+use mubert_cli::update_ip::CreateEntityFields;
+use mubert_cli::upload::{file_sha256, UploadOptions};
+use mubert_cli::verify::{verify_metadata, Problem};
+use std::cell::Cell;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const TOKEN: &str = "test-token";
+
+async fn mock(script: MockScript) -> MockFingerprint {
+    MockFingerprint::start(
+        "127.0.0.1:0",
+        MockScript {
+            auth_token: Some(TOKEN.into()),
+            ..script
+        },
+    )
+    .await
+    .unwrap()
+}
+
+fn api(mock: &MockFingerprint, token: Option<&str>) -> FingerprintApi {
+    FingerprintApi::new(&mock.url(), token.map(String::from), Duration::from_secs(5))
+        .unwrap()
+        .with_poll_options(PollOptions::every(Duration::from_millis(10)))
+        .with_upload_options(upload_options())
+}
+
+fn upload_options() -> UploadOptions {
+    UploadOptions {
+        chunk_size: 1000,
+        retry_delay: Duration::from_millis(1),
+        ..Default::default()
+    }
+}
+
+fn resumable_api(mock: &MockFingerprint) -> FingerprintApi {
+    api(mock, Some(TOKEN)).with_upload_options(UploadOptions {
+        resumable: true,
+        ..upload_options()
+    })
+}
+
+fn audio_file(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("mubert-{name}-{}.wav", std::process::id()));
+    let audio: Vec<u8> = (0..4096u32).map(|i| (i % 251) as u8).collect();
+    std::fs::write(&path, audio).unwrap();
+    path
+}
+
+fn entity_fields() -> CreateEntityFields {
+    let data = std::fs::read_to_string(
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/create_entity.json"),
+    )
+    .unwrap();
+    serde_json::from_str(&data).unwrap()
+}
+
+#[tokio::test]
+async fn test_off_chain_flow() {
+    let mock = mock(MockScript {
+        pending_polls: 2,
+        ..Default::default()
+    })
+    .await;
+    let api = api(&mock, Some(TOKEN));
+    let file = audio_file("flow");
+
+    let fingerprint = api.fingerprint(&file).await.unwrap();
+    assert_eq!(fingerprint, format!("{}/fingerprint/job-1", mock.url()));
+
+    let fields = entity_fields();
+    let metadata = api
+        .create_metadata(&fields.metadata_request(fingerprint.clone()))
+        .await
+        .unwrap();
+    assert_eq!(metadata.url, format!("{}/metadata/1", mock.url()));
+
+    let record = mock.record();
+    assert_eq!(record.uploads, vec![4096]);
+    assert_eq!(record.status_polls, 3);
+    assert_eq!(record.metadata[0]["title"], "example");
+    assert_eq!(record.metadata[0]["bpm"], 120);
+    assert_eq!(record.metadata[0]["fingerprint"], fingerprint.as_str());
+
+    std::fs::remove_file(file).unwrap();
+}
+
+#[tokio::test]
+async fn test_status_retried_after_failure() {
+    let mock = mock(MockScript {
+        failures: vec![MockFailure {
+            path: "/v1/fingerprint/status".into(),
+            status: 503,
+            times: Some(2),
+            body: "busy".into(),
+            ..Default::default()
+        }],
+        ..Default::default()
+    })
+    .await;
+    let api = api(&mock, Some(TOKEN));
+    let file = audio_file("retry");
+
+    api.fingerprint(&file).await.unwrap();
+    assert_eq!(mock.record().status_polls, 1);
+
+    std::fs::remove_file(file).unwrap();
+}
+
+#[tokio::test]
+async fn test_stuck_job_deadline() {
+    let mock = mock(MockScript {
+        pending_polls: u32::MAX,
+        ..Default::default()
+    })
+    .await;
+    let api = api(&mock, Some(TOKEN)).with_poll_options(PollOptions {
+        timeout: Duration::from_millis(200),
+        ..PollOptions::every(Duration::from_millis(10))
+    });
+    let file = audio_file("stuck");
+
+    let result = api.fingerprint(&file).await;
+    assert!(matches!(
+        result,
+        Err(Error::Timeout(ref e)) if e == "fingerprint job job-1 not done in 200ms, last status: processing"
+    ));
+    // the interval doubled, not polled every 10ms
+    assert!(mock.record().status_polls < 10);
+
+    std::fs::remove_file(file).unwrap();
+}
+
+#[tokio::test]
+async fn test_failed_job() {
+    let mock = mock(MockScript {
+        pending_polls: 1,
+        failed_jobs: true,
+        ..Default::default()
+    })
+    .await;
+    let api = api(&mock, Some(TOKEN)).with_poll_options(PollOptions {
+        timeout: Duration::from_secs(10),
+        ..PollOptions::every(Duration::from_millis(10))
+    });
+    let file = audio_file("failed");
+
+    let result = api.fingerprint(&file).await;
+    assert!(matches!(
+        result,
+        Err(Error::JobFailed(Waiting::FingerprintJob(ref id))) if id == "job-1"
+    ));
+    // stopped at the failed status, not polled until the deadline
+    assert_eq!(mock.record().status_polls, 2);
+
+    std::fs::remove_file(file).unwrap();
+}
+
+#[tokio::test]
+async fn test_api_errors() {
+    let mock = mock(MockScript {
+        failures: vec![MockFailure {
+            path: "/v1/fingerprint/create".into(),
+            status: 500,
+            times: None,
+            body: "storage is full".into(),
+            ..Default::default()
+        }],
+        ..Default::default()
+    })
+    .await;
+    let file = audio_file("errors");
+
+    let result = api(&mock, Some(TOKEN)).upload_audio(&file).await;
+    assert!(
+        matches!(result, Err(Error::Api { status: 500, ref body }) if body == "storage is full")
+    );
+    // failed again after the retries
+    assert!(mock.record().uploads.is_empty());
+
+    let result = api(&mock, Some("bad")).upload_audio(&file).await;
+    assert!(matches!(result, Err(Error::Api { status: 401, .. })));
+
+    let result = api(&mock, None).upload_audio(&file).await;
+    assert!(matches!(result, Err(Error::Config(_))));
+    assert!(mock.record().uploads.is_empty());
+
+    std::fs::remove_file(file).unwrap();
+}
+
+#[tokio::test]
+async fn test_request_timeout() {
+    let mock = mock(MockScript {
+        delay_ms: 500,
+        ..Default::default()
+    })
+    .await;
+    let api =
+        FingerprintApi::new(&mock.url(), Some(TOKEN.into()), Duration::from_millis(100)).unwrap();
+
+    let result = api.check_fingerprint_status("job-1").await;
+    assert!(matches!(result, Err(Error::Http(_))));
+}
+
+#[tokio::test]
+async fn test_streamed_upload() {
+    let mock = mock(MockScript::default()).await;
+    let api = api(&mock, Some(TOKEN));
+    let file = audio_file("stream");
+
+    let sent = Cell::new(0);
+    let job = api
+        .upload_audio_with_progress(&file, &|progress| sent.set(progress.sent))
+        .await
+        .unwrap();
+    assert_eq!(job.id, "job-1");
+    assert_eq!(sent.get(), 4096);
+
+    let record = mock.record();
+    assert_eq!(record.chunks, 0);
+    assert_eq!(
+        record.uploads_sha256,
+        vec![file_sha256(&file).await.unwrap()]
+    );
+
+    std::fs::remove_file(file).unwrap();
+}
+
+#[tokio::test]
+async fn test_resumable_upload() {
+    let mock = mock(MockScript {
+        resumable: true,
+        failures: vec![
+            MockFailure {
+                path: "/v1/fingerprint/upload/".into(),
+                status: 503,
+                times: Some(1),
+                ..Default::default()
+            },
+            MockFailure {
+                path: "/v1/fingerprint/upload/".into(),
+                drop: true,
+                times: Some(1),
+                ..Default::default()
+            },
+        ],
+        ..Default::default()
+    })
+    .await;
+    let api = resumable_api(&mock);
+    let file = audio_file("resume");
+
+    let sent = Cell::new(0);
+    let job = api
+        .upload_audio_with_progress(&file, &|progress| sent.set(progress.sent))
+        .await
+        .unwrap();
+    assert_eq!(job.id, "job-1");
+    assert!(!job.reused);
+    assert_eq!(sent.get(), 4096);
+
+    let record = mock.record();
+    assert_eq!(record.chunks, 5);
+    assert_eq!(record.uploads, vec![4096]);
+    assert_eq!(
+        record.uploads_sha256,
+        vec![file_sha256(&file).await.unwrap()]
+    );
+
+    // a new upload of the complete content gets its job without sending it
+    let job = api.upload_audio(&file).await.unwrap();
+    assert_eq!(job.id, "job-1");
+    assert_eq!(mock.record().chunks, 5);
+
+    std::fs::remove_file(file).unwrap();
+}
+
+#[tokio::test]
+async fn test_upload_session_fallback() {
+    // an api without sessions answering a client error other than 404
+    let mock = mock(MockScript {
+        resumable: true,
+        failures: vec![MockFailure {
+            path: "/v1/fingerprint/upload".into(),
+            method: Some("POST".into()),
+            status: 400,
+            ..Default::default()
+        }],
+        ..Default::default()
+    })
+    .await;
+    let file = audio_file("fallback");
+
+    let job = resumable_api(&mock).upload_audio(&file).await.unwrap();
+    assert_eq!(job.id, "job-1");
+    let record = mock.record();
+    assert_eq!(record.chunks, 0);
+    assert_eq!(record.uploads, vec![4096]);
+
+    // sessions are only used when asked for
+    let job = api(&mock, Some(TOKEN)).upload_audio(&file).await.unwrap();
+    assert_eq!(job.id, "job-2");
+    assert_eq!(mock.record().chunks, 0);
+
+    // an auth failure is not an api without sessions
+    let mock = self::mock(MockScript {
+        resumable: true,
+        failures: vec![MockFailure {
+            path: "/v1/fingerprint/upload".into(),
+            status: 403,
+            ..Default::default()
+        }],
+        ..Default::default()
+    })
+    .await;
+    let result = resumable_api(&mock).upload_audio(&file).await;
+    assert!(matches!(result, Err(Error::Api { status: 403, .. })));
+
+    std::fs::remove_file(file).unwrap();
+}
+
+#[tokio::test]
+async fn test_job_cache() {
+    let mock = mock(MockScript::default()).await;
+    let job_cache = std::env::temp_dir().join(format!("mubert-jobs-{}.json", std::process::id()));
+    let options = UploadOptions {
+        job_cache: Some(job_cache.clone()),
+        ..Default::default()
+    };
+    let api = api(&mock, Some(TOKEN)).with_upload_options(options.clone());
+    let file = audio_file("cache");
+
+    let job = api.upload_audio(&file).await.unwrap();
+    assert!(!job.reused);
+    let job = api.upload_audio(&file).await.unwrap();
+    assert!(job.reused);
+    assert_eq!(job.id, "job-1");
+    assert_eq!(mock.record().uploads.len(), 1);
+
+    // the job is unknown to another api
+    let other = self::mock(MockScript::default()).await;
+    let job = self::api(&other, Some(TOKEN))
+        .with_upload_options(options)
+        .upload_audio(&file)
+        .await
+        .unwrap();
+    assert!(!job.reused);
+    assert_eq!(other.record().uploads.len(), 1);
+
+    std::fs::remove_file(file).unwrap();
+    std::fs::remove_file(job_cache).unwrap();
+}
+
+#[tokio::test]
+async fn test_verify_metadata() {
+    let mock = mock(MockScript::default()).await;
+    let api = api(&mock, Some(TOKEN));
+    let file = audio_file("verify");
+    let other =
+        std::env::temp_dir().join(format!("mubert-verify-other-{}.wav", std::process::id()));
+    std::fs::write(&other, b"other audio").unwrap();
+
+    let fingerprint = api.fingerprint(&file).await.unwrap();
+    let metadata = api
+        .create_metadata(&entity_fields().metadata_request(fingerprint.clone()))
+        .await
+        .unwrap();
+
+    // fingerprinted again as job-2, which serves the same fingerprint
+    let verification = verify_metadata(&api, &metadata.url, None, Some(&file))
+        .await
+        .unwrap();
+    assert!(verification.is_ok(), "{:?}", verification.problems);
+    assert_eq!(verification.metadata.unwrap().fingerprint, fingerprint);
+    assert_eq!(
+        verification.local_fingerprint,
+        Some(format!("{}/fingerprint/job-2", mock.url()))
+    );
+
+    let verification = verify_metadata(&api, &metadata.url, None, Some(&other))
+        .await
+        .unwrap();
+    assert_eq!(
+        verification.problems,
+        vec![Problem::FingerprintMismatch {
+            metadata: fingerprint,
+            local: format!("{}/fingerprint/job-3", mock.url()),
+        }]
+    );
+
+    let missing = format!("{}/metadata/9", mock.url());
+    let verification = verify_metadata(&api, &missing, None, None).await.unwrap();
+    assert!(matches!(
+        &verification.problems[..],
+        [Problem::Unreachable { url, .. }] if *url == missing
+    ));
+
+    std::fs::remove_file(file).unwrap();
+    std::fs::remove_file(other).unwrap();
+}